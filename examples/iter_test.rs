@@ -2,32 +2,47 @@ use std::time::Instant;
 
 use toybox::*;
 
+#[component]
+struct Velocity {
+    velocity: Vector3,
+}
+
+#[derive(Bundle)]
+struct MoverBundle {
+    location: Location,
+    velocity: Velocity,
+}
+
 fn main() {
+    let mut world = World::default();
     let start = Instant::now();
     const NUM: usize = 1000000;
-    let mut location: Vec<_> = (0..NUM).map(|_| Location::new(0f32, 0f32, 0f32)).collect();
-    let velocity: Vec<_> = (0..NUM).map(|_| Location::new(10f32, 0f32, 0f32)).collect();
+    world.spawn_batch((0..NUM).map(|_| MoverBundle {
+        location: Location::new(0f32, 0f32, 0f32),
+        velocity: Velocity {
+            velocity: Vector3::new(10f32, 0f32, 0f32),
+        },
+    }));
     let after_entity_generation = Instant::now();
     println!(
         "create entity cost: {}ms",
         (after_entity_generation - start).as_millis()
     );
 
-    let after_scheduler_setup = Instant::now();
+    let (velocity, mut location) =
+        unsafe { <(RBWComps<Velocity>, WriteComps<Location>)>::fetch(&world) };
+    let after_fetch_components = Instant::now();
     println!(
-        "setup scheduler cost: {}ms",
-        (after_scheduler_setup - after_entity_generation).as_millis()
+        "fetch components cost: {}ms",
+        (after_fetch_components - after_entity_generation).as_millis()
     );
 
-    location
-        .iter_mut()
-        .zip(velocity.iter())
-        .for_each(|(location, velocity)| {
-            location.location.x += velocity.location.x * 0.1f32;
-            location.location.y += velocity.location.y * 0.1f32;
-            location.location.z += velocity.location.z * 0.1f32;
-        });
+    (&velocity, &mut location).join().for_each(
+        |(velocity, location): (&Velocity, &mut Location)| {
+            location.location += velocity.velocity * 0.1f32;
+        },
+    );
 
-    let elapsed = after_scheduler_setup.elapsed();
-    println!("scheduler update cost: {}ms", elapsed.as_millis())
+    let elapsed = after_fetch_components.elapsed();
+    println!("update cost: {}ms", elapsed.as_millis())
 }
@@ -0,0 +1,132 @@
+//! Time structural changes under parallel load. The locked baseline applies every change under
+//! the write lock of `Entities` at once, as before the changes were deferred, the deferred path
+//! queues them until the sync point.
+
+use std::time::{Duration, Instant};
+
+use toybox::*;
+
+#[component]
+struct Velocity {
+    velocity: Vector3,
+}
+
+#[component]
+struct Health {
+    health: f32,
+}
+
+const NUM: usize = 200000;
+
+fn measure(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn compare(name: &str, locked: Duration, deferred: Duration) {
+    println!(
+        "{}: locked {}ms, deferred {}ms, {:.1}x",
+        name,
+        locked.as_millis(),
+        deferred.as_millis(),
+        locked.as_secs_f64() / deferred.as_secs_f64()
+    );
+}
+
+fn create_entities(world: &mut World, locked: bool) -> Duration {
+    measure(|| {
+        let entities = unsafe { world.fetch::<Entities>() };
+        (0..NUM).into_par_iter().for_each(|_| {
+            entities.new_entity();
+            if locked {
+                entities.maintain();
+            }
+        });
+        world.maintain();
+    })
+}
+
+// Two systems insert components while a third joins
+fn insert_while_joining(world: &mut World, locked: bool) -> Duration {
+    let spawned = world.spawn_batch((0..NUM).map(|_| (Location::new(0f32, 0f32, 0f32),)));
+    measure(|| {
+        {
+            let (locations, mut velocities, mut healths) = unsafe {
+                <(RBWComps<Location>, WriteComps<Velocity>, WriteComps<Health>)>::fetch(world)
+            };
+            let entities = unsafe { world.fetch::<Entities>() };
+            rayon::scope(|s| {
+                s.spawn(|_| {
+                    (&locations).join().count();
+                });
+                s.spawn(|_| {
+                    for &entity in &spawned {
+                        velocities.insert(
+                            entity,
+                            Velocity {
+                                velocity: Vector3::new(1f32, 0f32, 0f32),
+                            },
+                        );
+                        if locked {
+                            entities.maintain();
+                        }
+                    }
+                });
+                s.spawn(|_| {
+                    for &entity in &spawned {
+                        healths.insert(entity, Health { health: 1f32 });
+                        if locked {
+                            entities.maintain();
+                        }
+                    }
+                });
+            });
+        }
+        world.maintain();
+    })
+}
+
+fn main() {
+    println!("threads: {}", rayon::current_num_threads());
+
+    let mut locked_world = World::default();
+    let mut deferred_world = World::default();
+    for world in vec![&mut locked_world, &mut deferred_world] {
+        world.insert(Entities::default);
+        world.insert_components::<Velocity>();
+        world.insert_components::<Health>();
+    }
+
+    compare(
+        "create entities",
+        create_entities(&mut locked_world, true),
+        create_entities(&mut deferred_world, false),
+    );
+    assert_eq!(unsafe { locked_world.fetch::<Entities>() }.len(), NUM);
+    assert_eq!(unsafe { deferred_world.fetch::<Entities>() }.len(), NUM);
+
+    // Component indices were looked up in the registry behind its lock, now they are cached
+    let name = concat!(module_path!(), "::Velocity");
+    let by_name = measure(|| {
+        (0..NUM * 10).into_par_iter().for_each(|_| {
+            ComponentIndex::by_name(name).unwrap();
+        });
+    });
+    let by_type = measure(|| {
+        (0..NUM * 10).into_par_iter().for_each(|_| {
+            ComponentIndex::get::<Velocity>();
+        });
+    });
+    println!(
+        "component index: registry {}ms, cached {}ms",
+        by_name.as_millis(),
+        by_type.as_millis()
+    );
+
+    compare(
+        "insert while joining",
+        insert_while_joining(&mut locked_world, true),
+        insert_while_joining(&mut deferred_world, false),
+    );
+}
@@ -0,0 +1,89 @@
+//! Reloaded by the hot reload tests, rebuilt with a different `RELOAD_FIXTURE_STEP` in between.
+//! Also run as a remote plugin.
+//! The hooks append what they see to a `Vec<String>` resource if the test inserted one,
+//! a std type is the same type in the test and in the fixture.
+
+use toybox::*;
+
+struct ReloadFixture {}
+
+#[component(name = "reload_fixture.Counter")]
+struct Counter {
+    value: i32,
+    runs: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Runs {
+    count: u32,
+    /// Crash at the run, for the remote plugin tests
+    #[serde(default)]
+    panic_at: Option<u32>,
+}
+
+#[system]
+struct CountSystem {}
+
+impl<'s> System<'s> for CountSystem {
+    type SystemData = (Write<'s, Runs>, WriteComps<'s, Counter>);
+
+    fn setup(&mut self, world: &mut World) {
+        world.insert_components::<Counter>();
+        log_hook(world, "setup");
+    }
+
+    fn teardown(&mut self, world: &mut World) {
+        log_hook(world, "teardown");
+    }
+
+    fn run(&mut self, (mut runs, mut counters): Self::SystemData) {
+        let step: i32 = option_env!("RELOAD_FIXTURE_STEP")
+            .unwrap_or("1")
+            .parse()
+            .unwrap();
+        runs.count += 1;
+        if runs.panic_at == Some(runs.count) {
+            panic!("reload_fixture panics at run {}", runs.count);
+        }
+        for counter in (&mut counters).join() {
+            counter.value += step;
+            counter.runs = runs.count;
+        }
+    }
+}
+
+impl Plugin for ReloadFixture {
+    fn name(&self) -> &'static str {
+        "reload_fixture"
+    }
+
+    fn on_load(&self, world: &mut World) {
+        log_hook(world, "on_load");
+        world.insert(Runs::default);
+    }
+
+    fn on_unload(&self, world: &mut World) {
+        log_hook(world, "on_unload");
+    }
+
+    fn resources(&self) -> Vec<PluginResource> {
+        vec![PluginResource::new::<Runs>("reload_fixture.Runs")]
+    }
+}
+
+fn log_hook(world: &World, hook: &str) {
+    let counters = unsafe { world.try_fetch::<ComponentStorage<Counter>>() }
+        .ok()
+        .map(|counters| counters.len());
+    let line = format!(
+        "{} runs:{} counters:{:?}",
+        hook,
+        world.contains::<Runs>(),
+        counters
+    );
+    if let Ok(log) = unsafe { world.try_fetch_mut::<Vec<String>>() } {
+        log.push(line);
+    }
+}
+
+declare_plugin!(ReloadFixture {});
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use tb_engine::app_info::{AppInfo, LaunchMethod};
+use tb_engine::cli::{LogLevel, SettingsOverrides};
+use tb_plugin::PluginManifest;
+
+use crate::errors::*;
+use crate::{load_manifest, rebuild, scaffold};
+
+/// `toybox new <name>`, in the project dir if it is given, or in `<name>` in the current dir.
+/// The project depends on the toybox source in `engine`, or the one the binary is built from.
+pub fn new(app_info: &AppInfo, name: &str, engine: Option<&Path>) -> Result<()> {
+    let dir = match &app_info.method {
+        LaunchMethod::Project { project_dir } => project_dir.clone(),
+        LaunchMethod::Archive => app_info.project_root_dir.join(name),
+    };
+    let engine = match engine {
+        Some(engine) => engine.to_owned(),
+        None => scaffold::find_engine_source_dir()
+            .ok_or("The toybox source dir is not found from the binary, give it by --engine")?,
+    };
+    scaffold::new_project(&dir, name, &engine)?;
+    if app_info.settings.log_enabled(LogLevel::Info) {
+        println!("Created project {} in: {:?}", name, dir);
+    }
+    Ok(())
+}
+
+/// `toybox build`
+pub fn build(app_info: &AppInfo) -> Result<()> {
+    let project_dir = &app_info.project_root_dir;
+    let result = rebuild::build(project_dir, &app_info.settings)?;
+    if !result.success {
+        bail!("Failed to build project: {:?}", project_dir);
+    }
+    if app_info.settings.log_enabled(LogLevel::Info) {
+        for artifact in &result.artifacts {
+            println!("built: {:?}", artifact);
+        }
+    }
+    Ok(())
+}
+
+/// `toybox check`, the project compiles, its manifest and config are valid,
+/// and its entry level exists
+pub fn check(app_info: &AppInfo) -> Result<()> {
+    let project_dir = &app_info.project_root_dir;
+    let mut problems = vec![];
+    if !rebuild::cargo(project_dir, "check", &app_info.settings)?.success {
+        problems.push("the project doesn't compile".to_owned());
+    }
+    if let Err(e) = load_manifest(project_dir) {
+        problems.push(e.display_chain().to_string());
+    }
+    let entry_level = app_info
+        .project_assets_dir
+        .join(&app_info.settings.entry_level);
+    if !entry_level.exists() {
+        problems.push(format!("entry level not found: {:?}", entry_level));
+    }
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{}", problem);
+        }
+        bail!("Check failed: {:?}", project_dir);
+    }
+    if app_info.settings.log_enabled(LogLevel::Info) {
+        println!("Check passed: {:?}", project_dir);
+    }
+    Ok(())
+}
+
+/// `toybox pack`, build the project and collect its plugins, assets, manifest and config.
+/// The engine runs the packed project when started in its dir.
+pub fn pack(app_info: &AppInfo, output: Option<&Path>) -> Result<()> {
+    let project_dir = &app_info.project_root_dir;
+    let result = rebuild::build(project_dir, &app_info.settings)?;
+    if !result.success {
+        bail!("Failed to build project: {:?}", project_dir);
+    }
+    let output = match output {
+        Some(output) => output.to_owned(),
+        None => project_dir.join("target/pack"),
+    };
+    std::fs::create_dir_all(&output).chain_err(|| format!("Failed to create dir: {:?}", output))?;
+    for artifact in &result.artifacts {
+        copy_file(artifact, &output.join(artifact.file_name().unwrap()))?;
+    }
+    for file_name in &[
+        PluginManifest::file_name(),
+        SettingsOverrides::config_file_name(),
+    ] {
+        let file = project_dir.join(file_name);
+        if file.exists() {
+            copy_file(&file, &output.join(file_name))?;
+        }
+    }
+    if app_info.project_assets_dir.exists() {
+        copy_dir(
+            &app_info.project_assets_dir,
+            &output.join(AppInfo::assets_dir_name()),
+        )?;
+    }
+    if app_info.settings.log_enabled(LogLevel::Info) {
+        println!("Packed into: {:?}", output);
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    std::fs::copy(from, to).chain_err(|| format!("Failed to copy {:?} to {:?}", from, to))?;
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to).chain_err(|| format!("Failed to create dir: {:?}", to))?;
+    let entries =
+        std::fs::read_dir(from).chain_err(|| format!("Failed to read dir: {:?}", from))?;
+    for entry in entries {
+        let entry = entry.chain_err(|| format!("Failed to read dir: {:?}", from))?;
+        let path = entry.path();
+        let target = to.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir(&path, &target)?;
+        } else {
+            copy_file(&path, &target)?;
+        }
+    }
+    Ok(())
+}
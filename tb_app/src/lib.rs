@@ -1,13 +1,22 @@
-use std::process::Command;
+use std::path::Path;
 use std::time::{Duration, Instant};
 
 use errors::*;
 use tb_ecs::*;
 use tb_engine::app_info::{AppInfo, LaunchMethod};
 use tb_engine::asset::AssetLoader;
+use tb_engine::cli::{Cli, CliCommand, LogLevel, Settings};
 use tb_engine::level::{Level, LevelManager};
 use tb_engine::path::TbPath;
-use tb_plugin::PluginManager;
+use tb_engine::time::{AppExit, Time};
+use tb_plugin::{PluginManager, PluginManifest};
+
+pub use rebuild::{BuildResult, ProjectWatcher};
+pub use scaffold::{find_engine_source_dir, new_project};
+
+mod commands;
+mod rebuild;
+mod scaffold;
 
 mod errors {
     pub use tb_core::error::*;
@@ -16,40 +25,123 @@ mod errors {
 }
 
 #[derive(Default)]
-pub struct Application {}
+pub struct Application {
+    plugin_manager: PluginManager,
+    watcher: Option<ProjectWatcher>,
+    manifest: Option<PluginManifest>,
+    settings: Settings,
+    frame_limit: Option<u64>,
+    simulated_clock: bool,
+}
 
 impl Application {
+    /// Stop after `frames` frames, run until `AppExit` is inserted otherwise
+    pub fn with_frame_limit(mut self, frames: u64) -> Self {
+        self.frame_limit = Some(frames);
+        self
+    }
+
+    /// Run the frames without sleeping, `Time` advances by the frame duration of the fps setting
+    pub fn with_simulated_clock(mut self) -> Self {
+        self.simulated_clock = true;
+        self
+    }
+
+    /// The fps and log level, the defaults if not given
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn plugin_manager(&mut self) -> &mut PluginManager {
+        &mut self.plugin_manager
+    }
+
+    /// Run the frames on `world` and return it, the project and entry level are not set up.
+    /// The returned world may hold the components and resources of the plugins, so their
+    /// libraries stay open after the application is dropped, see `PluginManager`.
+    /// Insert an `AssetLoader` for the dirs of the project beforehand,
+    /// the default one resolves the paths in `AppDirs::default`.
+    /// For automated tests, with the plugins added to `plugin_manager` beforehand.
+    pub fn run_world(&mut self, mut world: World) -> World {
+        self.main_loop(&mut world);
+        world
+    }
+
     pub fn run() -> Result<()> {
-        let mut app = Self::default();
+        #[cfg(unix)]
+        if let Some(result) = tb_plugin::serve_remote_plugin() {
+            return result.chain_err(|| "Failed to serve remote plugin");
+        }
+        let app_info = match AppInfo::from_args(std::env::args().skip(1)) {
+            Ok(app_info) => app_info,
+            Err(e) => {
+                eprint!("{}", Cli::usage());
+                return Err(e).chain_err(|| "Failed to start");
+            }
+        };
+        match &app_info.command {
+            CliCommand::Run => {}
+            CliCommand::Help => {
+                print!("{}", Cli::usage());
+                return Ok(());
+            }
+            CliCommand::New { name, engine } => {
+                return commands::new(&app_info, name, engine.as_deref())
+            }
+            CliCommand::Build => return commands::build(&app_info),
+            CliCommand::Pack { output } => return commands::pack(&app_info, output.as_deref()),
+            CliCommand::Check => return commands::check(&app_info),
+        }
+
+        let settings = &app_info.settings;
+        let mut app = Self {
+            settings: settings.clone(),
+            frame_limit: settings.frames,
+            simulated_clock: settings.headless,
+            ..Default::default()
+        };
         let mut world = World::default();
-        app.setup_project(&mut world)?;
-        app.setup_entry_level(&mut world)?;
+        app.setup_project(&app_info)?;
+        app.setup_entry_level(&mut world, &app_info)?;
         app.main_loop(&mut world);
         Ok(())
     }
 
-    fn setup_project(&mut self, world: &mut World) -> Result<()> {
-        let plugin_manager: &mut PluginManager = world.insert(PluginManager::default);
-        let app_info = AppInfo::get();
+    fn setup_project(&mut self, app_info: &AppInfo) -> Result<()> {
+        let settings = &app_info.settings;
+        let profile = settings.profile;
         match &app_info.method {
             LaunchMethod::Project { project_dir } => {
                 if !project_dir.exists() {
                     bail!("project not exists. path: {:?}", project_dir);
                 }
-                Command::new("cargo")
-                    .current_dir(project_dir)
-                    .arg("build")
-                    .status()
-                    .chain_err(|| "Failed to build project")?;
-                let project_lib_dir = if cfg!(debug_assertions) {
-                    project_dir.join("target/debug")
+                if !rebuild::build(project_dir, settings)?.success {
+                    eprintln!("Failed to build project, loading the last built plugins");
+                }
+                self.watcher = Some(ProjectWatcher::spawn(project_dir.clone(), settings.clone()));
+                self.plugin_manager
+                    .add_search_dir(project_dir.join("target").join(profile.dir_name()));
+                self.manifest = load_manifest(project_dir)?;
+                if self.manifest.is_some() {
+                    self.discover_plugins();
                 } else {
-                    project_dir.join("target/release")
-                };
-                plugin_manager.add_search_dir(project_lib_dir);
-                plugin_manager.add_plugin(project_dir.file_name().unwrap().to_str().unwrap())
+                    // without a manifest, only the plugin of the project itself
+                    let lib_name = project_dir.file_name().unwrap().to_str().unwrap();
+                    self.plugin_manager
+                        .add_plugin(lib_name)
+                        .chain_err(|| "Failed to load project plugin")?;
+                }
+            }
+            // a project packed by `toybox pack`, if there is a manifest
+            LaunchMethod::Archive => {
+                self.manifest = load_manifest(&app_info.project_root_dir)?;
+                if self.manifest.is_some() {
+                    self.plugin_manager
+                        .add_search_dir(app_info.project_root_dir.clone());
+                    self.discover_plugins();
+                }
             }
-            LaunchMethod::Archive => {}
         }
 
         if !app_info.project_assets_dir.exists() {
@@ -63,10 +155,17 @@ impl Application {
         Ok(())
     }
 
-    fn setup_entry_level(&self, world: &mut World) -> Result<()> {
-        let path = TbPath::new_project_assets("levels/entry.tbasset");
+    fn discover_plugins(&mut self) {
+        let report = self.plugin_manager.discover(self.manifest.as_ref());
+        if self.settings.log_enabled(LogLevel::Info) {
+            print!("{}", report);
+        }
+    }
+
+    fn setup_entry_level(&self, world: &mut World, app_info: &AppInfo) -> Result<()> {
+        let path = TbPath::new_project_assets(&self.settings.entry_level);
         world.insert(LevelManager::default);
-        world.insert(AssetLoader::default);
+        world.insert(|| AssetLoader::new(app_info.dirs()));
         let (mut level_manager, mut asset_loader) =
             unsafe { <(Write<LevelManager>, Write<AssetLoader>)>::fetch(world) };
         let level = asset_loader.load::<Level>(path);
@@ -74,15 +173,55 @@ impl Application {
         Ok(())
     }
 
+    /// Hand the plugins rebuilt in the background to the plugin manager, they are reloaded in
+    /// its next update
+    fn load_rebuilt_plugins(&mut self) {
+        let result = match self.watcher.as_ref().and_then(ProjectWatcher::try_recv) {
+            Some(result) => result,
+            None => return,
+        };
+        if !result.success {
+            eprintln!("Failed to rebuild project, keeping the loaded plugins");
+            return;
+        }
+        for artifact in &result.artifacts {
+            if let Err(e) = self
+                .plugin_manager
+                .add_artifact(artifact, self.manifest.as_ref())
+            {
+                eprintln!("{}", e.display_chain());
+            }
+        }
+    }
+
+    /// Run the frames until the frame limit is reached or `AppExit` is inserted,
+    /// then tear down the systems
     fn main_loop(&mut self, world: &mut World) {
         let mut scheduler = Scheduler::new(world);
-        const FPS: f32 = 30f32;
-        let frame_duration = Duration::from_secs_f32(1f32 / FPS);
-        loop {
+        let frame_duration = Duration::from_secs_f32(1f32 / self.settings.fps);
+        world.insert(Time::default);
+        let mut last_start = None;
+        let mut frames = 0;
+        while self.frame_limit.map_or(true, |limit| frames < limit) {
             let start = Instant::now();
+            let delta = match last_start {
+                Some(last_start) if !self.simulated_clock => start - last_start,
+                _ => frame_duration,
+            };
+            last_start = Some(start);
+            unsafe { world.fetch_mut::<Time>() }.advance(delta);
 
+            self.load_rebuilt_plugins();
+            self.plugin_manager.update(world, &mut scheduler);
             scheduler.update(world);
+            frames += 1;
 
+            if unsafe { world.try_fetch::<AppExit>() }.is_ok() {
+                break;
+            }
+            if self.simulated_clock {
+                continue;
+            }
             let elapsed = start.elapsed();
             if frame_duration > elapsed {
                 let should_sleep = frame_duration - elapsed;
@@ -91,5 +230,17 @@ impl Application {
                 std::thread::yield_now();
             }
         }
+        scheduler.shutdown(world);
+    }
+}
+
+/// The plugin manifest in `dir`, if there is one
+fn load_manifest(dir: &Path) -> Result<Option<PluginManifest>> {
+    let path = dir.join(PluginManifest::file_name());
+    if !path.exists() {
+        return Ok(None);
     }
+    PluginManifest::load(&path)
+        .map(Some)
+        .chain_err(|| "Failed to load plugin manifest")
 }
@@ -0,0 +1,245 @@
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+use tb_core::serde_json;
+use tb_engine::cli::{LogLevel, Profile, Settings};
+
+use crate::errors::*;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How long the files have to stay unchanged before they are built
+const QUIET_PERIOD: Duration = Duration::from_millis(300);
+
+/// The messages of `cargo build --message-format=json` used here
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum CargoMessage {
+    CompilerMessage {
+        message: Diagnostic,
+    },
+    CompilerArtifact {
+        target: Target,
+        filenames: Vec<PathBuf>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    kind: Vec<String>,
+}
+
+pub struct BuildResult {
+    pub success: bool,
+    /// The dynamic libraries built, fresh ones included
+    pub artifacts: Vec<PathBuf>,
+}
+
+/// Print the diagnostics, and collect the artifacts from the messages of cargo
+fn read_messages(messages: impl Iterator<Item = String>, settings: &Settings) -> Vec<PathBuf> {
+    let mut artifacts = vec![];
+    for line in messages {
+        match serde_json::from_str(&line) {
+            Ok(CargoMessage::CompilerMessage { message }) => match message.rendered {
+                Some(rendered) if settings.log_enabled(LogLevel::Warn) => {
+                    eprint!("{}", rendered)
+                }
+                _ => {}
+            },
+            Ok(CargoMessage::CompilerArtifact { target, filenames }) => {
+                if target.kind.iter().any(|kind| kind == "dylib") {
+                    artifacts.extend(filenames.into_iter().filter(|filename| {
+                        filename
+                            .to_str()
+                            .map_or(false, |f| f.ends_with(std::env::consts::DLL_SUFFIX))
+                    }));
+                }
+            }
+            Ok(CargoMessage::Other) => {}
+            // e.g. printed by a build script
+            Err(_) => println!("{}", line),
+        }
+    }
+    artifacts
+}
+
+/// Build the project with the profile of `settings`, the diagnostics are printed
+pub fn build(project_dir: &Path, settings: &Settings) -> Result<BuildResult> {
+    cargo(project_dir, "build", settings)
+}
+
+/// Run the cargo command on the project, e.g. `build` or `check`
+pub(crate) fn cargo(project_dir: &Path, command: &str, settings: &Settings) -> Result<BuildResult> {
+    let mut cargo = Command::new("cargo");
+    cargo
+        .current_dir(project_dir)
+        .args(&[command, "--message-format=json"])
+        .stdout(Stdio::piped());
+    if settings.profile == Profile::Release {
+        cargo.arg("--release");
+    }
+    let mut child = cargo
+        .spawn()
+        .chain_err(|| format!("Failed to run cargo {}", command))?;
+    let stdout = child.stdout.take().unwrap();
+    let artifacts = read_messages(
+        BufReader::new(stdout).lines().filter_map(|line| line.ok()),
+        settings,
+    );
+    let status = child
+        .wait()
+        .chain_err(|| format!("Failed to run cargo {}", command))?;
+    Ok(BuildResult {
+        success: status.success(),
+        artifacts,
+    })
+}
+
+/// Rebuild the project in the background when its files change.
+/// The watching thread is stopped on drop, after the build in progress if there is one.
+pub struct ProjectWatcher {
+    results: Receiver<BuildResult>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ProjectWatcher {
+    /// The modification times of the files are polled, `target` and hidden dirs are skipped.
+    /// A change is built once the files stay unchanged for a quiet period,
+    /// e.g. while an editor saves several files.
+    pub fn spawn(project_dir: PathBuf, settings: Settings) -> Self {
+        let (sender, results) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = std::thread::spawn(move || {
+            let mut last_modified = latest_modified(&project_dir);
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(POLL_INTERVAL);
+                let mut modified = latest_modified(&project_dir);
+                if modified <= last_modified {
+                    continue;
+                }
+                loop {
+                    std::thread::sleep(QUIET_PERIOD);
+                    let latest = latest_modified(&project_dir);
+                    if latest == modified {
+                        break;
+                    }
+                    modified = latest;
+                }
+                last_modified = modified;
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                if settings.log_enabled(LogLevel::Info) {
+                    println!("Project changed, rebuilding: {:?}", project_dir);
+                }
+                match build(&project_dir, &settings) {
+                    Ok(result) => {
+                        if sender.send(result).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => eprintln!("{}", e.display_chain()),
+                }
+            }
+        });
+        Self {
+            results,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// The latest build finished since the last call
+    pub fn try_recv(&self) -> Option<BuildResult> {
+        self.results.try_iter().last()
+    }
+}
+
+impl Drop for ProjectWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn latest_modified(dir: &Path) -> SystemTime {
+    let mut latest = SystemTime::UNIX_EPOCH;
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return latest,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with('.') || file_name == "target" {
+            continue;
+        }
+        let modified = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => latest_modified(&entry.path()),
+            _ => entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        };
+        latest = latest.max(modified);
+    }
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn cargo_messages() {
+        let lib = format!(
+            "/project/target/debug/{}game{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        );
+        let messages = vec![
+            r#"{"reason":"compiler-message","message":{"rendered":"warning: unused\n"}}"#
+                .to_owned(),
+            r#"{"reason":"compiler-artifact","target":{"kind":["lib"]},"filenames":["/project/target/debug/libdep.rlib"]}"#.to_owned(),
+            serde_json::json!({
+                "reason": "compiler-artifact",
+                "target": { "kind": ["dylib", "rlib"] },
+                "filenames": [lib, "/project/target/debug/libgame.rlib"],
+            })
+            .to_string(),
+            r#"{"reason":"build-finished","success":true}"#.to_owned(),
+        ];
+        assert_eq!(
+            read_messages(messages.into_iter(), &Settings::default()),
+            vec![PathBuf::from(lib)]
+        );
+    }
+    #[test]
+    fn stop_watcher() {
+        let project_dir = std::env::temp_dir().join("tb_app_stop_watcher");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        let watcher = ProjectWatcher::spawn(project_dir, Settings::default());
+        let start = Instant::now();
+        drop(watcher);
+        assert!(start.elapsed() < POLL_INTERVAL * 2);
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+
+use tb_core::path_util;
+use tb_engine::app_info::AppInfo;
+use tb_engine::asset::AssetLoader;
+use tb_engine::cli::{Settings, SettingsOverrides};
+use tb_engine::level::Level;
+use tb_plugin::PluginManifest;
+
+use crate::errors::*;
+
+/// The files of a new project by path, `{{name}}`, `{{plugin_type}}` and `{{toybox_path}}`
+/// in them are replaced
+fn templates() -> Vec<(&'static str, &'static str)> {
+    vec![
+        (
+            "Cargo.toml",
+            include_str!("../templates/new_project/Cargo.toml"),
+        ),
+        (
+            "src/lib.rs",
+            include_str!("../templates/new_project/lib.rs"),
+        ),
+        (
+            ".gitignore",
+            include_str!("../templates/new_project/gitignore"),
+        ),
+        (
+            PluginManifest::file_name(),
+            include_str!("../templates/new_project/plugins.json"),
+        ),
+        (
+            SettingsOverrides::config_file_name(),
+            include_str!("../templates/new_project/toybox.json"),
+        ),
+    ]
+}
+
+/// The toybox source dir the running binary is built in, found in the ancestors of its dir,
+/// e.g. of `target/debug`
+pub fn find_engine_source_dir() -> Option<PathBuf> {
+    path_util::exe_dir()
+        .ancestors()
+        .find(|dir| {
+            std::fs::read_to_string(dir.join("Cargo.toml"))
+                .map(|manifest| {
+                    manifest
+                        .lines()
+                        .any(|line| line.trim() == r#"name = "toybox""#)
+                })
+                .unwrap_or(false)
+        })
+        .map(Path::to_owned)
+}
+
+/// Create a buildable project in `dir`, with a plugin named `name`, a sample component and
+/// system, a test and an entry level. `toybox_dir` is the toybox crate it depends on.
+pub fn new_project(dir: &Path, name: &str, toybox_dir: &Path) -> Result<()> {
+    let valid_name = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid_name {
+        bail!(
+            "Invalid project name: {}, use lowercase letters, digits and underscores",
+            name
+        );
+    }
+    let is_empty_dir = |dir: &Path| {
+        std::fs::read_dir(dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false)
+    };
+    if dir.exists() && !is_empty_dir(dir) {
+        bail!("Project dir is not empty: {:?}", dir);
+    }
+
+    let plugin_type: String = name
+        .split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect();
+    let toybox_path = toybox_dir
+        .to_str()
+        .ok_or_else(|| format!("Toybox dir is not valid UTF-8: {:?}", toybox_dir))?;
+    let toybox_path = toml_string(toybox_path);
+    let render = |template: &str| {
+        template
+            .replace("{{name}}", name)
+            .replace("{{plugin_type}}", &plugin_type)
+            .replace("{{toybox_path}}", &toybox_path)
+    };
+    for (path, template) in templates() {
+        write_file(&dir.join(path), &render(template))?;
+    }
+
+    let entry_level = dir
+        .join(AppInfo::assets_dir_name())
+        .join(Settings::default().entry_level);
+    create_parent_dir(&entry_level)?;
+    AssetLoader::write_file(&entry_level, Level::default())
+        .chain_err(|| "Failed to create entry level")?;
+    Ok(())
+}
+
+/// A TOML basic string of `value`
+fn toml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).chain_err(|| format!("Failed to create dir: {:?}", dir))?;
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    create_parent_dir(path)?;
+    std::fs::write(path, content).chain_err(|| format!("Failed to write file: {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::toml_string;
+
+    #[test]
+    fn toml_path() {
+        assert_eq!(toml_string("/home/toybox"), r#""/home/toybox""#);
+        assert_eq!(toml_string(r#"C:\toy "box""#), r#""C:\\toy \"box\"""#);
+        assert_eq!(toml_string("a\tb"), r#""a\u0009b""#);
+    }
+}
@@ -0,0 +1,59 @@
+use toybox::*;
+
+struct {{plugin_type}} {}
+
+/// A sample component, turned by `SpinSystem`
+#[component(name = "{{name}}.Spin")]
+struct Spin {
+    speed: f32,
+    angle: f32,
+}
+
+#[system]
+struct SpinSystem {}
+
+impl<'s> System<'s> for SpinSystem {
+    type SystemData = WriteComps<'s, Spin>;
+
+    fn setup(&mut self, world: &mut World) {
+        world.insert_components::<Spin>();
+    }
+
+    fn run(&mut self, mut spins: Self::SystemData) {
+        for spin in (&mut spins).join() {
+            spin.angle += spin.speed;
+        }
+    }
+}
+
+impl Plugin for {{plugin_type}} {
+    fn name(&self) -> &'static str {
+        "{{name}}"
+    }
+}
+
+declare_plugin!({{plugin_type}} {});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spin() {
+        let mut world = World::default();
+        let mut system = SpinSystem {};
+        system.setup(&mut world);
+        let entity = world
+            .create_entity()
+            .with(Spin {
+                speed: 2.0,
+                angle: 1.0,
+            })
+            .create();
+
+        system.run(unsafe { WriteComps::<Spin>::fetch(&world) });
+
+        let spins = unsafe { world.fetch_components::<Spin>() };
+        assert_eq!(spins.fetch(entity).unwrap().angle, 3.0);
+    }
+}
@@ -1,7 +1,5 @@
 use std::marker::PhantomData;
 
-use rayon::iter::Filter;
-
 use crate::*;
 
 pub struct AntiComponents<'r, S: 'r + Storage, C: Component, A: AccessOrder> {
@@ -21,8 +19,8 @@ pub struct AntiComponent<C: Component> {
 impl<'r, S: 'r + Storage, C: Component, A: AccessOrder> Join<'r> for AntiComponents<'r, S, C, A> {
     type Element = AntiComponent<C>;
     type ElementFetcher = AntiComponentsFetch<'r, S, C, A>;
-    type EntitiesIter = std::iter::Filter<EntitiesIter<'r>, impl FnMut(&Entity) -> bool>;
-    type ParEntitiesIter = Filter<ParEntitiesIter<'r>, impl Fn(&Entity) -> bool + Sync + Send>;
+    type EntitiesIter = MatchedEntitiesIter<'r>;
+    type ParEntitiesIter = ParMatchedEntitiesIter<'r>;
 
     fn open(mut self) -> (Self::EntitiesIter, Self::ElementFetcher) {
         (self.matched_entities_iter(), self.elem_fetcher())
@@ -47,17 +45,11 @@ impl<'r, S: 'r + Storage, C: Component, A: AccessOrder> Join<'r> for AntiCompone
     }
 
     fn matched_entities_iter(&self) -> Self::EntitiesIter {
-        let storage = &self.components.storage;
-        self.entities()
-            .iter()
-            .filter(move |&entity| !storage.contains(entity))
+        MatchedEntitiesIter::get::<Self>(self.entities().read())
     }
 
     fn par_matched_entities_iter(&self) -> Self::ParEntitiesIter {
-        let storage = &self.components.storage;
-        self.entities()
-            .par_iter()
-            .filter(move |&entity| !storage.contains(entity))
+        ParMatchedEntitiesIter::get::<Self>(self.entities().read())
     }
 
     fn fill_matcher(matcher: &mut ArchetypeMatcher) {
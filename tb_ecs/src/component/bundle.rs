@@ -0,0 +1,120 @@
+use crate::*;
+
+/// A group of components inserted into an entity at once
+pub trait Bundle: 'static + Send + Sync {
+    fn component_indices() -> Vec<ComponentIndex>;
+
+    /// Insert the storages of the bundle components and reserve `additional` elements in them.
+    fn reserve_storages(world: &mut World, additional: usize);
+
+    /// Insert the bundle components into their storages
+    ///
+    /// # Safety
+    ///
+    /// The storages must be inserted by `reserve_storages`,
+    /// and no one else can access them at the same time.
+    unsafe fn insert_into(self, world: &World, entity: Entity);
+}
+
+macro_rules! impl_bundle_tuple {
+    () => {};
+    ($c0:ident $(, $c1:ident)*) => {
+        impl_bundle_tuple!($($c1),*);
+
+        impl<$c0: Component, $($c1: Component),*> Bundle for ($c0, $($c1,)*) {
+            fn component_indices() -> Vec<ComponentIndex> {
+                vec![ComponentIndex::get::<$c0>(), $(ComponentIndex::get::<$c1>()),*]
+            }
+
+            fn reserve_storages(world: &mut World, additional: usize) {
+                world.insert_components::<$c0>().reserve(additional);
+                $(world.insert_components::<$c1>().reserve(additional);)*
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn insert_into(self, world: &World, entity: Entity) {
+                let ($c0, $($c1,)*) = self;
+                world.fetch_components_mut::<$c0>().insert(entity, $c0);
+                $(world.fetch_components_mut::<$c1>().insert(entity, $c1);)*
+            }
+        }
+    };
+}
+
+impl_bundle_tuple!(C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14, C15);
+
+impl World {
+    /// Create an entity with all components of the bundle
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        self.spawn_batch(std::iter::once(bundle))[0]
+    }
+
+    /// Create entities for every bundle.
+    /// The entities are created directly in their final archetype and the storages are reserved ahead.
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) -> Vec<Entity> {
+        let bundles: Vec<B> = bundles.into_iter().collect();
+        B::reserve_storages(self, bundles.len());
+        let entities = self
+            .insert(Entities::default)
+            .new_entities(&B::component_indices(), bundles.len());
+        for (&entity, bundle) in entities.iter().zip(bundles) {
+            unsafe { bundle.insert_into(self, entity) };
+        }
+        entities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[component]
+    struct Position {
+        value: i32,
+    }
+
+    #[component]
+    struct Speed {
+        value: i32,
+    }
+
+    #[derive(Bundle)]
+    struct MoverBundle {
+        position: Position,
+        speed: Speed,
+    }
+
+    #[test]
+    fn spawn_batch() {
+        let mut world = World::default();
+        let entities = world.spawn_batch((0..100).map(|i| MoverBundle {
+            position: Position { value: i },
+            speed: Speed { value: 1 },
+        }));
+        assert_eq!(entities.len(), 100);
+
+        let (mut positions, speeds) =
+            unsafe { <(WriteComps<Position>, RBWComps<Speed>)>::fetch(&world) };
+        let mut count = 0;
+        for (position, speed) in (&mut positions, &speeds).join() {
+            position.value += speed.value;
+            count += 1;
+        }
+        assert_eq!(count, 100);
+
+        let positions = unsafe { world.fetch_components::<Position>() };
+        for (i, &entity) in entities.iter().enumerate() {
+            assert_eq!(positions.fetch(entity).unwrap().value, i as i32 + 1);
+        }
+    }
+
+    #[test]
+    fn spawn_tuple() {
+        let mut world = World::default();
+        let entity = world.spawn((Position { value: 1 },));
+        let entities = unsafe { world.fetch::<Entities>() };
+        assert!(entities.is_alive(entity));
+        let positions = unsafe { world.fetch_components::<Position>() };
+        assert_eq!(positions.fetch(entity).unwrap().value, 1);
+    }
+}
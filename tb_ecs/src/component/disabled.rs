@@ -0,0 +1,147 @@
+use std::marker::PhantomData;
+
+use crate::*;
+
+/// Marks an entity as disabled, joins skip disabled entities unless `Join::include_disabled`.
+/// The components of a disabled entity are kept.
+#[component(name = "tb_ecs::Disabled")]
+pub struct Disabled;
+
+pub struct IncludeDisabled<J> {
+    join: J,
+}
+
+impl<J> IncludeDisabled<J> {
+    pub(crate) fn new(join: J) -> Self {
+        Self { join }
+    }
+}
+
+pub struct DisabledIncluded<E> {
+    _phantom: PhantomData<E>,
+}
+
+impl<'j, J: Join<'j>> Join<'j> for IncludeDisabled<J> {
+    type Element = DisabledIncluded<J::Element>;
+    type ElementFetcher = J::ElementFetcher;
+    type EntitiesIter = MatchedEntitiesIter<'j>;
+    type ParEntitiesIter = ParMatchedEntitiesIter<'j>;
+
+    fn open(mut self) -> (Self::EntitiesIter, Self::ElementFetcher) {
+        (self.matched_entities_iter(), self.elem_fetcher())
+    }
+
+    fn par_open(mut self) -> (Self::ParEntitiesIter, Self::ElementFetcher) {
+        (self.par_matched_entities_iter(), self.elem_fetcher())
+    }
+
+    fn entities(&self) -> &'j Entities {
+        self.join.entities()
+    }
+
+    fn len(&self) -> usize {
+        self.join.len()
+    }
+
+    fn elem_fetcher(&mut self) -> Self::ElementFetcher {
+        self.join.elem_fetcher()
+    }
+
+    fn matched_entities_iter(&self) -> Self::EntitiesIter {
+        MatchedEntitiesIter::get::<Self>(self.entities().read())
+    }
+
+    fn par_matched_entities_iter(&self) -> Self::ParEntitiesIter {
+        ParMatchedEntitiesIter::get::<Self>(self.entities().read())
+    }
+
+    fn fill_matcher(matcher: &mut ArchetypeMatcher) {
+        J::fill_matcher(matcher);
+        matcher.include_disabled();
+    }
+}
+
+impl World {
+    /// Disable the entity without removing its components
+    pub fn disable(&mut self, entity: Entity) {
+        self.insert_components::<Disabled>();
+        let mut disabled = unsafe { WriteComps::<Disabled>::fetch(self) };
+        if disabled.entities.is_alive(entity) && !disabled.storage.contains(entity) {
+            disabled.insert(entity, Disabled);
+        }
+        self.maintain();
+    }
+
+    pub fn enable(&mut self, entity: Entity) {
+        if self.is_enabled(entity) {
+            return;
+        }
+        let mut disabled = unsafe { WriteComps::<Disabled>::fetch(self) };
+        disabled.remove(entity);
+        self.maintain();
+    }
+
+    pub fn is_enabled(&self, entity: Entity) -> bool {
+        unsafe { self.try_fetch::<ComponentStorage<Disabled>>() }
+            .map_or(true, |disabled| !disabled.contains(entity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[component]
+    struct Health {
+        value: i32,
+    }
+
+    #[component]
+    struct Armor {
+        value: i32,
+    }
+
+    #[test]
+    fn disable() {
+        let mut world = World::default();
+        let a = world
+            .create_entity()
+            .with(Health { value: 1 })
+            .with(Armor { value: 1 })
+            .create();
+        let b = world
+            .create_entity()
+            .with(Health { value: 2 })
+            .with(Armor { value: 2 })
+            .create();
+        world.disable(b);
+        assert!(world.is_enabled(a));
+        assert!(!world.is_enabled(b));
+
+        let (healths, armors) = unsafe { <(RBWComps<Health>, RBWComps<Armor>)>::fetch(&world) };
+        let values: Vec<_> = (&healths).join().map(|health| health.value).collect();
+        assert_eq!(values, vec![1]);
+        assert_eq!((&healths, &armors).join().count(), 1);
+        assert_eq!((&healths, !&armors).join().count(), 0);
+        assert_eq!((&healths).include_disabled().join().count(), 2);
+        assert_eq!((&healths, &armors).include_disabled().join().count(), 2);
+        let disabled: Vec<_> = unsafe { world.fetch_components::<Disabled>() }
+            .open()
+            .0
+            .copied()
+            .collect();
+        assert_eq!(disabled, vec![b]);
+        assert_eq!(
+            unsafe { world.fetch_components::<Health>() }
+                .fetch(b)
+                .unwrap()
+                .value,
+            2
+        );
+
+        world.enable(b);
+        assert!(world.is_enabled(b));
+        let healths = unsafe { RBWComps::<Health>::fetch(&world) };
+        assert_eq!((&healths).join().count(), 2);
+    }
+}
@@ -0,0 +1,762 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use errors::*;
+use tb_core::serde_json::{self, Value};
+use tb_core::*;
+
+use crate::registry::{ComponentIndex, ComponentOperation, ComponentRegistry, RestoredStorage};
+use crate::{ArchetypeMatcher, Entities, Entity, LocalToWorldLink, World};
+
+mod errors {
+    pub use tb_core::error::*;
+
+    use crate::Entity;
+
+    error_chain! {
+        errors {
+            DeadEntity(entity: Entity) {
+                description("Entity is not alive"),
+                display("Entity is not alive. entity: {:?}", entity),
+            }
+            UnknownDynamicComponent(name: String) {
+                description("Unknown dynamic component"),
+                display("Unknown dynamic component. name: {}", name),
+            }
+            DynamicLayoutConflict(name: String) {
+                description("Component name is registered with another layout"),
+                display("Component name is registered with another layout. name: {}", name),
+            }
+            DynamicLayoutMismatch(name: String) {
+                description("Value doesn't match the layout of the dynamic component"),
+                display("Value doesn't match the layout of the dynamic component. name: {}", name),
+            }
+        }
+    }
+}
+
+/// How the values of a dynamic component are stored
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DynamicLayout {
+    /// A json object with the fields
+    Json(Vec<DynamicField>),
+    /// Raw bytes of the size
+    Bytes(usize),
+}
+
+impl DynamicLayout {
+    pub fn is_matched(&self, value: &DynamicValue) -> bool {
+        match (self, value) {
+            (DynamicLayout::Json(fields), DynamicValue::Json(value)) => {
+                is_object_matched(fields, value)
+            }
+            (DynamicLayout::Bytes(size), DynamicValue::Bytes(bytes)) => bytes.len() == *size,
+            _ => false,
+        }
+    }
+
+    /// Visit the values of the `Entity` fields
+    pub(crate) fn for_each_entity(
+        &self,
+        value: &mut DynamicValue,
+        action: &mut impl FnMut(&mut Entity),
+    ) {
+        if let (DynamicLayout::Json(fields), DynamicValue::Json(value)) = (self, value) {
+            for_each_object_entity(fields, value, action);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct DynamicField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: DynamicFieldType,
+}
+
+/// `Entity` fields are remapped when the entities are cloned or merged,
+/// like typed references without a kill policy.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DynamicFieldType {
+    Bool,
+    Integer,
+    Number,
+    String,
+    Entity,
+    Array(Box<DynamicFieldType>),
+    Object(Vec<DynamicField>),
+    Any,
+}
+
+impl DynamicFieldType {
+    pub fn is_matched(&self, value: &Value) -> bool {
+        match self {
+            DynamicFieldType::Bool => value.is_boolean(),
+            DynamicFieldType::Integer => value.is_i64() || value.is_u64(),
+            DynamicFieldType::Number => value.is_number(),
+            DynamicFieldType::String => value.is_string(),
+            DynamicFieldType::Entity => Entity::deserialize(value).is_ok(),
+            DynamicFieldType::Array(element) => value
+                .as_array()
+                .map_or(false, |array| array.iter().all(|e| element.is_matched(e))),
+            DynamicFieldType::Object(fields) => is_object_matched(fields, value),
+            DynamicFieldType::Any => true,
+        }
+    }
+
+    fn for_each_entity(&self, value: &mut Value, action: &mut impl FnMut(&mut Entity)) {
+        match self {
+            DynamicFieldType::Entity => {
+                if let Ok(entity) = Entity::deserialize(&*value) {
+                    let mut mapped = entity;
+                    action(&mut mapped);
+                    if mapped != entity {
+                        *value = serde_json::to_value(mapped).unwrap();
+                    }
+                }
+            }
+            DynamicFieldType::Array(element) => {
+                if let Some(array) = value.as_array_mut() {
+                    for value in array {
+                        element.for_each_entity(value, action);
+                    }
+                }
+            }
+            DynamicFieldType::Object(fields) => for_each_object_entity(fields, value, action),
+            _ => {}
+        }
+    }
+}
+
+fn is_object_matched(fields: &[DynamicField], value: &Value) -> bool {
+    value.as_object().map_or(false, |object| {
+        fields.iter().all(|field| {
+            object
+                .get(&field.name)
+                .map_or(false, |value| field.ty.is_matched(value))
+        })
+    })
+}
+
+fn for_each_object_entity(
+    fields: &[DynamicField],
+    value: &mut Value,
+    action: &mut impl FnMut(&mut Entity),
+) {
+    if let Some(object) = value.as_object_mut() {
+        for field in fields {
+            if let Some(value) = object.get_mut(&field.name) {
+                field.ty.for_each_entity(value, action);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DynamicValue {
+    Json(Value),
+    Bytes(Vec<u8>),
+}
+
+impl DynamicValue {
+    pub fn as_json(&self) -> Option<&Value> {
+        match self {
+            DynamicValue::Json(value) => Some(value),
+            DynamicValue::Bytes(_) => None,
+        }
+    }
+    pub fn as_json_mut(&mut self) -> Option<&mut Value> {
+        match self {
+            DynamicValue::Json(value) => Some(value),
+            DynamicValue::Bytes(_) => None,
+        }
+    }
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            DynamicValue::Json(_) => None,
+            DynamicValue::Bytes(bytes) => Some(bytes),
+        }
+    }
+    pub fn as_bytes_mut(&mut self) -> Option<&mut [u8]> {
+        match self {
+            DynamicValue::Json(_) => None,
+            DynamicValue::Bytes(bytes) => Some(bytes),
+        }
+    }
+}
+
+/// A component type registered at runtime, e.g. by scripts or data files
+pub struct DynamicComponentInfo {
+    name: String,
+    index: ComponentIndex,
+    layout: DynamicLayout,
+}
+
+impl DynamicComponentInfo {
+    /// Register a dynamic component type.
+    /// Registering a name again returns the same index, if the layout is the same.
+    pub fn register(name: &str, layout: DynamicLayout) -> Result<ComponentIndex> {
+        let mut layout = Some(layout);
+        let info = ComponentRegistry::register_dynamic(name, |index| DynamicComponentInfo {
+            name: name.to_owned(),
+            index,
+            layout: layout.take().unwrap(),
+        });
+        let dynamic = info
+            .dynamic()
+            .ok_or_else(|| ErrorKind::DynamicLayoutConflict(name.to_owned()))?;
+        match layout {
+            Some(layout) if layout != dynamic.layout => {
+                Err(ErrorKind::DynamicLayoutConflict(name.to_owned()).into())
+            }
+            _ => Ok(dynamic.index),
+        }
+    }
+
+    pub fn find(name: &str) -> Option<&'static DynamicComponentInfo> {
+        ComponentRegistry::info_by_name(name).and_then(|info| info.dynamic())
+    }
+
+    fn get(name: &str) -> Result<&'static DynamicComponentInfo> {
+        Self::find(name).ok_or_else(|| ErrorKind::UnknownDynamicComponent(name.to_owned()).into())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn index(&self) -> ComponentIndex {
+        self.index
+    }
+    pub fn layout(&self) -> &DynamicLayout {
+        &self.layout
+    }
+
+    /// Replace the references to the keys of `map` in the component of `entity`
+    ///
+    /// # Safety
+    ///
+    /// No one else can access `DynamicComponents` at the same time.
+    pub(crate) unsafe fn remap_entity_refs(
+        &self,
+        world: &World,
+        entity: Entity,
+        map: &HashMap<Entity, Entity>,
+    ) {
+        let value = world
+            .try_fetch_mut::<DynamicComponents>()
+            .ok()
+            .and_then(|dynamic_components| dynamic_components.storages.get_mut(&self.index))
+            .and_then(|storage| storage.fetch_mut(entity));
+        if let Some(value) = value {
+            self.layout.for_each_entity(value, &mut |e: &mut Entity| {
+                if let Some(&mapped) = map.get(e) {
+                    *e = mapped;
+                }
+            });
+        }
+    }
+
+    /// Serialize the storage as pairs of entities and values,
+    /// `None` if the world has no storage of it
+    pub(crate) fn save_storage(&self, world: &World) -> Option<serde_json::Result<Value>> {
+        let dynamic_components = unsafe { world.try_fetch::<DynamicComponents>() }.ok()?;
+        let storage = dynamic_components.storages.get(&self.index)?;
+        Some(serde_json::to_value(storage.iter().collect::<Vec<_>>()))
+    }
+
+    /// Replace the storage with one saved by `save_storage` in place, archetypes are not updated.
+    /// Values which no longer match the layout are dropped.
+    /// Returns the entities whose values were inserted or removed.
+    pub(crate) fn restore_storage(
+        &self,
+        world: &mut World,
+        value: Value,
+    ) -> serde_json::Result<RestoredStorage> {
+        let saved: HashMap<Entity, DynamicValue> = serde_json::from_value::<Vec<_>>(value)?
+            .into_iter()
+            .filter(|(_, value)| self.layout.is_matched(value))
+            .collect();
+        let storage = world
+            .insert(DynamicComponents::default)
+            .storages
+            .entry(self.index)
+            .or_default();
+        let mut restored = RestoredStorage::default();
+        restored.removed = storage
+            .entities
+            .iter()
+            .copied()
+            .filter(|entity| !saved.contains_key(entity))
+            .collect();
+        for &entity in &restored.removed {
+            storage.remove(entity);
+        }
+        for (entity, value) in saved {
+            if !storage.contains(entity) {
+                restored.inserted.push(entity);
+            }
+            storage.insert(entity, value);
+        }
+        Ok(restored)
+    }
+}
+
+pub(crate) struct DynamicOperation {
+    index: ComponentIndex,
+}
+
+impl DynamicOperation {
+    pub(crate) fn new(index: ComponentIndex) -> Self {
+        Self { index }
+    }
+}
+
+impl ComponentOperation for DynamicOperation {
+    unsafe fn remove_from_world(&self, world: &World, entity: Entity) {
+        if let Ok(dynamic_components) = world.try_fetch_mut::<DynamicComponents>() {
+            if let Some(storage) = dynamic_components.storages.get_mut(&self.index) {
+                storage.remove(entity);
+            }
+        }
+    }
+
+    unsafe fn clone_component(&self, world: &World, from: Entity, to: Entity) {
+        if let Ok(dynamic_components) = world.try_fetch_mut::<DynamicComponents>() {
+            if let Some(storage) = dynamic_components.storages.get_mut(&self.index) {
+                if let Some(value) = storage.fetch(from).cloned() {
+                    storage.insert(to, value);
+                }
+            }
+        }
+    }
+
+    unsafe fn move_components(&self, from: &World, to: &mut World, link: &LocalToWorldLink) {
+        let storage = match from
+            .try_fetch_mut::<DynamicComponents>()
+            .ok()
+            .and_then(|dynamic_components| dynamic_components.storages.remove(&self.index))
+        {
+            Some(storage) => storage,
+            None => {
+                return;
+            }
+        };
+        let dynamic_components = to.insert(DynamicComponents::default);
+        for (local, value) in storage.entities.into_iter().zip(storage.values) {
+            if let Some(entity) = link.get(local) {
+                dynamic_components.insert(self.index, entity, value);
+            }
+        }
+    }
+
+    fn remove_storage(&self, world: &mut World) {
+        if let Ok(dynamic_components) = unsafe { world.try_fetch_mut::<DynamicComponents>() } {
+            dynamic_components.storages.remove(&self.index);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DynamicStorage {
+    values: Vec<DynamicValue>,
+    entities: Vec<Entity>,
+    entity_to_index: HashMap<Entity, usize>,
+}
+
+impl DynamicStorage {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entity_to_index.contains_key(&entity)
+    }
+    pub fn fetch(&self, entity: Entity) -> Option<&DynamicValue> {
+        self.entity_to_index
+            .get(&entity)
+            .map(|&index| &self.values[index])
+    }
+    pub fn fetch_mut(&mut self, entity: Entity) -> Option<&mut DynamicValue> {
+        let values = &mut self.values;
+        self.entity_to_index
+            .get(&entity)
+            .map(move |&index| &mut values[index])
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &DynamicValue)> {
+        self.entities.iter().copied().zip(self.values.iter())
+    }
+
+    fn insert(&mut self, entity: Entity, value: DynamicValue) {
+        match self.entity_to_index.entry(entity) {
+            Entry::Occupied(occupied) => self.values[*occupied.get()] = value,
+            Entry::Vacant(vacant) => {
+                vacant.insert(self.values.len());
+                self.values.push(value);
+                self.entities.push(entity);
+            }
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<DynamicValue> {
+        let removed_index = self.entity_to_index.remove(&entity)?;
+        let last_entity = *self.entities.last().unwrap();
+        self.entities.swap_remove(removed_index);
+        let removed = self.values.swap_remove(removed_index);
+        if last_entity != entity {
+            self.entity_to_index.insert(last_entity, removed_index);
+        }
+        Some(removed)
+    }
+}
+
+/// Storages of all dynamic components in a world
+#[derive(Default)]
+pub struct DynamicComponents {
+    storages: HashMap<ComponentIndex, DynamicStorage>,
+}
+
+impl DynamicComponents {
+    /// Insert the value into the storage, archetypes are not updated.
+    pub(crate) fn insert(&mut self, index: ComponentIndex, entity: Entity, value: DynamicValue) {
+        self.storages
+            .entry(index)
+            .or_default()
+            .insert(entity, value);
+    }
+
+    pub fn storage(&self, name: &str) -> Option<&DynamicStorage> {
+        DynamicComponentInfo::find(name).and_then(|info| self.storages.get(&info.index))
+    }
+
+    pub fn storage_mut(&mut self, name: &str) -> Option<&mut DynamicStorage> {
+        DynamicComponentInfo::find(name).and_then(move |info| self.storages.get_mut(&info.index))
+    }
+
+    /// Visit the entities matched by the query, with their values in the order of `query`.
+    pub fn join<'d>(
+        &'d self,
+        entities: &Entities,
+        query: &DynamicQuery,
+        mut on_each: impl FnMut(Entity, &[&'d DynamicValue]),
+    ) {
+        let storages: Option<Vec<&DynamicStorage>> = query
+            .all
+            .iter()
+            .map(|index| self.storages.get(index))
+            .collect();
+        let storages = match storages {
+            Some(storages) => storages,
+            None => return,
+        };
+        let mut values = Vec::with_capacity(storages.len());
+        for entity in entities.matched_entities(&query.matcher()) {
+            values.clear();
+            for storage in &storages {
+                match storage.fetch(entity) {
+                    Some(value) => values.push(value),
+                    None => break,
+                }
+            }
+            // the archetypes may be behind the storages until the next sync point
+            if values.len() == storages.len() {
+                on_each(entity, &values);
+            }
+        }
+    }
+
+    /// Visit the entities matched by the query, with their mutable values in the order of `query`.
+    pub fn join_mut(
+        &mut self,
+        entities: &Entities,
+        query: &DynamicQuery,
+        mut on_each: impl FnMut(Entity, &mut [&mut DynamicValue]),
+    ) {
+        let mut storages = Vec::with_capacity(query.all.len());
+        for index in &query.all {
+            match self.storages.get_mut(index) {
+                Some(storage) => storages.push(storage as *mut DynamicStorage),
+                None => return,
+            }
+        }
+        let mut values = Vec::with_capacity(storages.len());
+        for entity in entities.matched_entities(&query.matcher()) {
+            // the query indices are distinct, so every value is borrowed from another storage
+            for &storage in &storages {
+                match unsafe { (*storage).fetch_mut(entity) } {
+                    Some(value) => values.push(value),
+                    None => break,
+                }
+            }
+            // the archetypes may be behind the storages until the next sync point
+            if values.len() == storages.len() {
+                on_each(entity, &mut values);
+            }
+            values.clear();
+        }
+    }
+}
+
+/// Match entities by the names of dynamic components
+#[derive(Default)]
+pub struct DynamicQuery {
+    all: Vec<ComponentIndex>,
+    none: Vec<ComponentIndex>,
+    include_disabled: bool,
+}
+
+impl DynamicQuery {
+    /// Entities must have all components of `names`, duplicated names are ignored.
+    pub fn new(names: &[&str]) -> Result<Self> {
+        let mut query = Self::default();
+        for name in names {
+            let index = DynamicComponentInfo::get(name)?.index;
+            if !query.all.contains(&index) {
+                query.all.push(index);
+            }
+        }
+        Ok(query)
+    }
+
+    /// Entities must not have the component of `name`
+    pub fn without(mut self, name: &str) -> Result<Self> {
+        self.none.push(DynamicComponentInfo::get(name)?.index);
+        Ok(self)
+    }
+
+    /// Also match the disabled entities
+    pub fn include_disabled(mut self) -> Self {
+        self.include_disabled = true;
+        self
+    }
+
+    fn matcher(&self) -> ArchetypeMatcher {
+        let mut matcher = ArchetypeMatcher::default();
+        self.all.iter().for_each(|&index| matcher.add_all(index));
+        self.none.iter().for_each(|&index| matcher.add_none(index));
+        if self.include_disabled {
+            matcher.include_disabled();
+        }
+        matcher
+    }
+}
+
+impl World {
+    /// Insert the value of a dynamic component, the value must match the registered layout.
+    pub fn insert_dynamic(
+        &mut self,
+        entity: Entity,
+        name: &str,
+        value: DynamicValue,
+    ) -> Result<()> {
+        let info = DynamicComponentInfo::get(name)?;
+        if !info.layout.is_matched(&value) {
+            bail!(ErrorKind::DynamicLayoutMismatch(name.to_owned()));
+        }
+        if !self.insert(Entities::default).is_alive(entity) {
+            bail!(ErrorKind::DeadEntity(entity));
+        }
+        self.insert(DynamicComponents::default)
+            .insert(info.index, entity, value);
+        self.insert(Entities::default)
+            .on_component_index_inserted(entity, info.index);
+        self.maintain();
+        Ok(())
+    }
+
+    pub fn remove_dynamic(&mut self, entity: Entity, name: &str) -> Result<Option<DynamicValue>> {
+        let info = DynamicComponentInfo::get(name)?;
+        let removed = self
+            .insert(DynamicComponents::default)
+            .storages
+            .get_mut(&info.index)
+            .and_then(|storage| storage.remove(entity));
+        if removed.is_some() {
+            self.insert(Entities::default)
+                .on_component_index_removed(entity, info.index);
+            self.maintain();
+        }
+        Ok(removed)
+    }
+
+    pub fn fetch_dynamic(&self, entity: Entity, name: &str) -> Option<&DynamicValue> {
+        let dynamic_components = unsafe { self.try_fetch::<DynamicComponents>() }.ok()?;
+        dynamic_components.storage(name)?.fetch(entity)
+    }
+
+    /// Visit the entities which have all dynamic components of `names`
+    pub fn dynamic_join(
+        &self,
+        names: &[&str],
+        on_each: impl FnMut(Entity, &[&DynamicValue]),
+    ) -> Result<()> {
+        let query = DynamicQuery::new(names)?;
+        unsafe {
+            if let (Ok(entities), Ok(dynamic_components)) = (
+                self.try_fetch::<Entities>(),
+                self.try_fetch::<DynamicComponents>(),
+            ) {
+                dynamic_components.join(entities, &query, on_each);
+            }
+        }
+        Ok(())
+    }
+
+    /// Visit the entities which have all dynamic components of `names` with mutable values
+    pub fn dynamic_join_mut(
+        &mut self,
+        names: &[&str],
+        on_each: impl FnMut(Entity, &mut [&mut DynamicValue]),
+    ) -> Result<()> {
+        let query = DynamicQuery::new(names)?;
+        unsafe {
+            if let (Ok(entities), Ok(dynamic_components)) = (
+                self.try_fetch::<Entities>(),
+                self.try_fetch_mut::<DynamicComponents>(),
+            ) {
+                dynamic_components.join_mut(entities, &query, on_each);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tb_core::serde_json::{self, json};
+
+    use crate::*;
+
+    fn register() {
+        let health = serde_json::from_value(json!({
+            "json": [
+                { "name": "value", "type": "number" },
+                { "name": "tags", "type": { "array": "string" } }
+            ]
+        }))
+        .unwrap();
+        DynamicComponentInfo::register("test.Health", health).unwrap();
+        DynamicComponentInfo::register("test.Blob", DynamicLayout::Bytes(4)).unwrap();
+    }
+
+    #[test]
+    fn register_dynamic() {
+        register();
+        let index = DynamicComponentInfo::find("test.Health").unwrap().index();
+        register();
+        assert_eq!(
+            DynamicComponentInfo::find("test.Health").unwrap().index(),
+            index
+        );
+        assert!(DynamicComponentInfo::register("test.Health", DynamicLayout::Bytes(1)).is_err());
+    }
+
+    #[test]
+    fn dynamic_join() {
+        register();
+        let mut world = World::default();
+        let e0 = world.create_entity().create();
+        let e1 = world.create_entity().create();
+        let health = DynamicValue::Json(json!({ "value": 10, "tags": ["a"] }));
+        world
+            .insert_dynamic(e0, "test.Health", health.clone())
+            .unwrap();
+        world.insert_dynamic(e1, "test.Health", health).unwrap();
+        world
+            .insert_dynamic(e1, "test.Blob", DynamicValue::Bytes(vec![0; 4]))
+            .unwrap();
+        assert!(world
+            .insert_dynamic(
+                e0,
+                "test.Health",
+                DynamicValue::Json(json!({ "value": "x" }))
+            )
+            .is_err());
+        assert!(world
+            .insert_dynamic(e0, "test.Blob", DynamicValue::Bytes(vec![0; 3]))
+            .is_err());
+
+        let mut matched = vec![];
+        world
+            .dynamic_join(&["test.Health", "test.Blob"], |entity, _| {
+                matched.push(entity)
+            })
+            .unwrap();
+        assert_eq!(matched, vec![e1]);
+
+        world
+            .dynamic_join_mut(&["test.Health"], |_, values| {
+                values[0].as_json_mut().unwrap()["value"] = json!(5);
+            })
+            .unwrap();
+        let value = world.fetch_dynamic(e0, "test.Health").unwrap();
+        assert_eq!(value.as_json().unwrap()["value"], json!(5));
+
+        world.remove_dynamic(e1, "test.Blob").unwrap();
+        let mut count = 0;
+        world
+            .dynamic_join(&["test.Blob"], |_, _| count += 1)
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let clone = world.clone_entity(e0);
+        assert!(world.fetch_dynamic(clone, "test.Health").is_some());
+        world.kill(e0);
+        assert!(world.fetch_dynamic(e0, "test.Health").is_none());
+        let health = DynamicValue::Json(json!({ "value": 1, "tags": [] }));
+        assert!(world.insert_dynamic(e0, "test.Health", health).is_err());
+
+        // the archetype of e1 still has the value removed from the storage
+        unsafe { world.fetch_mut::<DynamicComponents>() }
+            .storage_mut("test.Health")
+            .unwrap()
+            .remove(e1);
+        let mut matched = vec![];
+        world
+            .dynamic_join_mut(&["test.Health"], |entity, _| matched.push(entity))
+            .unwrap();
+        assert_eq!(matched, vec![clone]);
+    }
+
+    #[test]
+    fn remap_dynamic_entity_refs() {
+        let layout = serde_json::from_value(json!({
+            "json": [
+                { "name": "target", "type": "entity" },
+                { "name": "others", "type": { "array": "entity" } }
+            ]
+        }))
+        .unwrap();
+        DynamicComponentInfo::register("test.Targets", layout).unwrap();
+        let mut world = World::default();
+        let external = world.create_entity().create();
+        let leader = world.create_entity().create();
+        let follower = world.create_entity().create();
+        let targets = json!({ "target": leader, "others": [leader, external] });
+        world
+            .insert_dynamic(follower, "test.Targets", DynamicValue::Json(targets))
+            .unwrap();
+
+        let clones = world.clone_entities(&[leader, follower]);
+        let (leader_clone, follower_clone) = (clones[0], clones[1]);
+        let expected = json!({ "target": leader_clone, "others": [leader_clone, external] });
+        let value = world.fetch_dynamic(follower_clone, "test.Targets").unwrap();
+        assert_eq!(value.as_json(), Some(&expected));
+
+        let mut scratch = World::default();
+        let local = scratch.create_entity().create();
+        let targets = json!({ "target": local, "others": [external] });
+        scratch
+            .insert_dynamic(local, "test.Targets", DynamicValue::Json(targets))
+            .unwrap();
+        let link = world.merge_from(scratch);
+        let merged = link.get(local).unwrap();
+        let expected = json!({ "target": merged, "others": [external] });
+        let value = world.fetch_dynamic(merged, "test.Targets").unwrap();
+        assert_eq!(value.as_json(), Some(&expected));
+    }
+}
@@ -0,0 +1,318 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use errors::*;
+use tb_core::serde_json::Value;
+
+use crate::registry::{ComponentIndex, ComponentInfo, ComponentRegistry};
+use crate::{DynamicComponents, DynamicValue, Entities, Entity, World};
+
+mod errors {
+    pub use tb_core::error::*;
+
+    error_chain! {
+        errors {
+            NotAnObject {
+                description("Components of an entity must be a json object"),
+                display("Components of an entity must be a json object"),
+            }
+            UnknownComponent(name: String) {
+                description("Unknown component"),
+                display("Unknown component. name: {}", name),
+            }
+            InvalidComponent(name: String) {
+                description("Failed to create component from json"),
+                display("Failed to create component from json. name: {}", name),
+            }
+            InvalidStorage(name: String) {
+                description("Failed to save or restore component storage as json"),
+                display("Failed to save or restore component storage as json. name: {}", name),
+            }
+            UnsavableComponents(names: Vec<String>) {
+                description("Component types can't be saved as json"),
+                display("Component types can't be saved as json. names: {}", names.join(", ")),
+            }
+        }
+    }
+}
+
+/// Serialized component storages keyed by the stable names of the component types
+pub type ComponentsSnapshot = HashMap<String, Value>;
+
+enum PendingComponent {
+    Typed(&'static ComponentInfo, Box<dyn Any + Send>),
+    Dynamic(ComponentIndex, DynamicValue),
+}
+
+impl World {
+    /// Spawn an entity from a json object of component values keyed by component name.
+    /// Components are looked up by their stable names, or by their type names if unambiguous.
+    /// No entity is spawned if a component can't be created.
+    pub fn spawn_from_json(&mut self, value: &Value) -> Result<Entity> {
+        let object = value.as_object().ok_or(ErrorKind::NotAnObject)?;
+        let mut component_indices = Vec::with_capacity(object.len());
+        let mut components = Vec::with_capacity(object.len());
+        for (name, value) in object {
+            let (component_index, info) = ComponentRegistry::info_by_name_or_type_name(name)
+                .and_then(|info| Some((ComponentIndex::by_name(info.name())?, info)))
+                .ok_or_else(|| ErrorKind::UnknownComponent(name.clone()))?;
+            let component = match info.dynamic() {
+                Some(dynamic) => {
+                    let value = DynamicValue::Json(value.clone());
+                    if !dynamic.layout().is_matched(&value) {
+                        bail!(ErrorKind::InvalidComponent(name.clone()));
+                    }
+                    PendingComponent::Dynamic(component_index, value)
+                }
+                None => {
+                    let component = info
+                        .component_from_json(value.clone())
+                        .ok_or_else(|| ErrorKind::InvalidComponent(name.clone()))?
+                        .chain_err(|| ErrorKind::InvalidComponent(name.clone()))?;
+                    PendingComponent::Typed(info, component)
+                }
+            };
+            component_indices.push(component_index);
+            components.push(component);
+        }
+
+        let entity = self
+            .insert(Entities::default)
+            .new_entities(&component_indices, 1)[0];
+        for component in components {
+            match component {
+                PendingComponent::Typed(info, component) => {
+                    info.insert_any_component(self, entity, component)
+                }
+                PendingComponent::Dynamic(component_index, value) => self
+                    .insert(DynamicComponents::default)
+                    .insert(component_index, entity, value),
+            }
+        }
+        Ok(entity)
+    }
+
+    /// Serialize the storages of the component types into `snapshot`, e.g. before the plugin
+    /// registering them is reloaded. Typed components are saved through their serde impls as json
+    /// like `spawn_from_json`, dynamic components as their values.
+    /// The other types are still saved if one fails, the types which can't be created from json
+    /// are skipped and returned in the error.
+    pub fn save_component_types(
+        &self,
+        component_indices: &[ComponentIndex],
+        snapshot: &mut ComponentsSnapshot,
+    ) -> Result<()> {
+        let mut result = Ok(());
+        let mut unsavable = vec![];
+        for &component_index in component_indices {
+            let info = match ComponentRegistry::info(component_index) {
+                Some(info) => info,
+                None => continue,
+            };
+            match info.save_storage(self) {
+                Some(Some(Ok(value))) => {
+                    snapshot.insert(info.name().to_owned(), value);
+                }
+                Some(Some(Err(e))) => {
+                    if result.is_ok() {
+                        let kind = ErrorKind::InvalidStorage(info.name().to_owned());
+                        result = Err(Error::with_chain(e, kind));
+                    }
+                }
+                Some(None) => {}
+                None => unsavable.push(info.name().to_owned()),
+            }
+        }
+        if result.is_ok() && !unsavable.is_empty() {
+            result = Err(ErrorKind::UnsavableComponents(unsavable).into());
+        }
+        result
+    }
+
+    /// Restore the storages saved by `save_component_types` into the types registered by the same
+    /// names. The storages are replaced in place, only the entities whose components were inserted
+    /// or removed change archetypes. Types which are no longer registered are skipped,
+    /// the components of dead entities are dropped. The other types are still restored if one fails.
+    pub fn restore_component_types(&mut self, snapshot: ComponentsSnapshot) -> Result<()> {
+        let entities = self.insert(Entities::default);
+        entities.maintain();
+        let mut result = Ok(());
+        for (name, value) in snapshot {
+            let (component_index, info) = match ComponentIndex::by_name(&name)
+                .and_then(|index| Some((index, ComponentRegistry::info(index)?)))
+            {
+                Some(found) => found,
+                None => continue,
+            };
+            let restored = match info.restore_storage(self, value) {
+                Some(Ok(restored)) => restored,
+                Some(Err(e)) => {
+                    if result.is_ok() {
+                        result = Err(Error::with_chain(e, ErrorKind::InvalidStorage(name)));
+                    }
+                    continue;
+                }
+                None => continue,
+            };
+            let entities = unsafe { self.fetch::<Entities>() };
+            for entity in restored.removed {
+                entities.on_component_index_removed(entity, component_index);
+            }
+            for entity in restored.inserted {
+                if entities.is_alive(entity) {
+                    entities.on_component_index_inserted(entity, component_index);
+                } else {
+                    unsafe {
+                        ComponentRegistry::operation(component_index)
+                            .0
+                            .remove_from_world(self, entity);
+                    }
+                }
+            }
+        }
+        self.maintain();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tb_core::serde_json::json;
+
+    use crate::*;
+
+    #[component]
+    struct JsonPosition {
+        x: i32,
+        y: i32,
+    }
+
+    #[component(name = "test.JsonSpeed")]
+    struct JsonSpeed {
+        value: i32,
+    }
+
+    /// Generic components are not registered by `#[component]`, so not saved as json
+    #[component]
+    struct JsonGeneric<T> {
+        value: T,
+    }
+
+    #[test]
+    fn spawn_from_json() {
+        DynamicComponentInfo::register("test.JsonTag", DynamicLayout::Json(vec![])).unwrap();
+        let mut world = World::default();
+        let entity = world
+            .spawn_from_json(&json!({
+                "JsonPosition": { "x": 1, "y": 2 },
+                "test.JsonSpeed": { "value": 3 },
+                "test.JsonTag": {}
+            }))
+            .unwrap();
+
+        let joined: Vec<_> = {
+            let (positions, speeds) =
+                unsafe { <(RBWComps<JsonPosition>, RBWComps<JsonSpeed>)>::fetch(&world) };
+            (&positions, &speeds)
+                .join()
+                .map(|(position, speed)| (position.x, position.y, speed.value))
+                .collect()
+        };
+        assert_eq!(joined, vec![(1, 2, 3)]);
+        assert!(world.fetch_dynamic(entity, "test.JsonTag").is_some());
+
+        assert!(world.spawn_from_json(&json!({ "Unknown": {} })).is_err());
+        assert!(world
+            .spawn_from_json(&json!({ "JsonPosition": { "x": 1, "y": 2 }, "test.JsonSpeed": {} }))
+            .is_err());
+        assert_eq!(unsafe { world.fetch::<Entities>() }.len(), 1);
+    }
+
+    #[test]
+    fn save_and_restore_component_types() {
+        let mut world = World::default();
+        let entity = world
+            .create_entity()
+            .with(JsonPosition { x: 1, y: 2 })
+            .with(JsonSpeed { value: 3 })
+            .create();
+        let tag =
+            DynamicComponentInfo::register("test.JsonSaved", DynamicLayout::Bytes(1)).unwrap();
+        world
+            .insert_dynamic(entity, "test.JsonSaved", DynamicValue::Bytes(vec![4]))
+            .unwrap();
+        let index = ComponentIndex::get::<JsonSpeed>();
+        let mut snapshot = ComponentsSnapshot::default();
+        world
+            .save_component_types(&[index, tag], &mut snapshot)
+            .unwrap();
+        let mut names: Vec<_> = snapshot.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["test.JsonSaved", "test.JsonSpeed"]);
+
+        world.remove_component_types(&[index, tag]);
+        assert!(!world.contains::<ComponentStorage<JsonSpeed>>());
+        assert!(world.fetch_dynamic(entity, "test.JsonSaved").is_none());
+        world.restore_component_types(snapshot).unwrap();
+        let (positions, speeds) =
+            unsafe { <(RBWComps<JsonPosition>, RBWComps<JsonSpeed>)>::fetch(&world) };
+        assert_eq!((&positions, &speeds).join().count(), 1);
+        let speed = unsafe { world.fetch_components::<JsonSpeed>() }.fetch(entity);
+        assert_eq!(speed.unwrap().value, 3);
+        assert_eq!(
+            world.fetch_dynamic(entity, "test.JsonSaved"),
+            Some(&DynamicValue::Bytes(vec![4]))
+        );
+    }
+
+    #[test]
+    fn restore_component_types_in_place() {
+        let mut world = World::default();
+        let kept = world.create_entity().with(JsonSpeed { value: 1 }).create();
+        let removed = world.create_entity().with(JsonSpeed { value: 2 }).create();
+        let inserted = world.create_entity().create();
+        let index = ComponentIndex::get::<JsonSpeed>();
+        let mut snapshot = ComponentsSnapshot::default();
+        world.save_component_types(&[index], &mut snapshot).unwrap();
+
+        {
+            let mut speeds = unsafe { WriteComps::<JsonSpeed>::fetch(&world) };
+            speeds.remove(removed);
+            speeds.insert(inserted, JsonSpeed { value: 3 });
+            (&mut speeds).join().for_each(|speed| speed.value += 10);
+        }
+        world.restore_component_types(snapshot).unwrap();
+        let speeds = unsafe { RBWComps::<JsonSpeed>::fetch(&world) };
+        let mut joined: Vec<_> = (&speeds).join().map(|speed| speed.value).collect();
+        joined.sort_unstable();
+        assert_eq!(joined, vec![1, 2]);
+        let speeds = unsafe { world.fetch_components::<JsonSpeed>() };
+        assert_eq!(speeds.fetch(kept).unwrap().value, 1);
+        assert!(!speeds.contains(inserted));
+    }
+
+    #[test]
+    fn report_unsavable_component_types() {
+        let info: &'static ComponentInfo = Box::leak(Box::new(
+            ComponentInfo::new::<JsonGeneric<i32>>().with_name("test.JsonUnsavable"),
+        ));
+        ComponentRegistry::add_component_infos("test.json", Box::new(std::iter::once(info)))
+            .unwrap();
+        let mut world = World::default();
+        world
+            .create_entity()
+            .with(JsonGeneric { value: 1 })
+            .with(JsonSpeed { value: 2 })
+            .create();
+        let indices = [
+            ComponentIndex::get::<JsonGeneric<i32>>(),
+            ComponentIndex::get::<JsonSpeed>(),
+        ];
+        let mut snapshot = ComponentsSnapshot::default();
+        let error = world
+            .save_component_types(&indices, &mut snapshot)
+            .unwrap_err();
+        assert!(error.to_string().contains("test.JsonUnsavable"));
+        assert_eq!(snapshot.keys().collect::<Vec<_>>(), vec!["test.JsonSpeed"]);
+    }
+}
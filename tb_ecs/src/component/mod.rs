@@ -1,22 +1,72 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
 use std::marker::PhantomData;
 use std::ops::Not;
 
 pub use anti_components::*;
+pub use bundle::*;
+pub use disabled::*;
+pub use dynamic::*;
+pub use json::ComponentsSnapshot;
+pub use reflect::*;
 pub use registry::*;
+pub use secondary_index::*;
 pub use storage::*;
 pub use tb_core::*;
 
 use crate::*;
 
 mod anti_components;
+mod bundle;
+mod disabled;
+mod dynamic;
+mod json;
+mod referrers;
+mod reflect;
 pub(crate) mod registry;
+mod secondary_index;
 mod storage;
 
 #[serde_box]
-pub trait Component: 'static + Send + Sync + SerdeBoxSer + SerdeBoxDe {}
+pub trait Component: 'static + Send + Sync + SerdeBoxSer + SerdeBoxDe {
+    /// Keys of the fields declared by `#[component(index = "...")]`
+    fn index_keys(&self) -> Vec<(&'static str, IndexKey)> {
+        vec![]
+    }
+
+    /// The static cache of the component index, `#[component]` declares one for each registered type
+    fn index_cache() -> Option<&'static ComponentIndexCache>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Whether `#[component(index = "...")]` declares indexed fields,
+    /// the storage tracks the changed components for the indices only then
+    fn has_index() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+
+    /// Whether `#[entity_ref(...)]` declares kill policies,
+    /// the storage tracks the changed components for the referrers only then
+    fn has_kill_policy() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+}
 
 pub trait EntityRef {
     fn for_each(&mut self, action: &mut impl FnMut(&mut Entity));
+
+    /// Drop the references `keep` returns false for.
+    /// Returns false if a reference can't be dropped, e.g. a bare `Entity`.
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool;
 }
 
 pub trait ComponentWithEntityRef<'e>: Component {
@@ -24,6 +74,23 @@ pub trait ComponentWithEntityRef<'e>: Component {
     fn mut_entity_ref(&'e mut self) -> Self::Ref;
 }
 
+/// What to do with a component after an entity it references is killed.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum DanglingAction {
+    Keep,
+    RemoveComponent,
+    KillOwner,
+}
+
+/// Implemented by `#[component]` when a field declares a kill policy:
+/// `#[entity_ref(nullify)]`, `#[entity_ref(remove)]` or `#[entity_ref(cascade)]`.
+pub trait CleanDanglingEntityRef: Component {
+    fn on_entity_killed(&mut self, killed: Entity) -> DanglingAction;
+
+    /// Visit the references of the fields with a kill policy
+    fn for_each_policy_ref(&mut self, action: &mut impl FnMut(&mut Entity));
+}
+
 pub struct Components<'r, S: 'r + Storage, C: Component, A: AccessOrder> {
     entities: &'r Entities,
     storage: S,
@@ -63,15 +130,182 @@ impl<'r, C: Component> Storage for &'r mut ComponentStorage<C> {
     }
 }
 
-impl<'e> EntityRef for &'e mut Entity {
+impl EntityRef for Entity {
     fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
         action(self)
     }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        keep(self)
+    }
+}
+
+impl<'e, E: EntityRef + ?Sized> EntityRef for &'e mut E {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        (**self).for_each(action)
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        (**self).retain(keep)
+    }
+}
+
+impl<E: EntityRef + ?Sized> EntityRef for Box<E> {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        (**self).for_each(action)
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        (**self).retain(keep)
+    }
+}
+
+impl<E: EntityRef> EntityRef for Option<E> {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        if let Some(e) = self {
+            e.for_each(action)
+        }
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        if let Some(e) = self {
+            if !e.retain(keep) {
+                *self = None;
+            }
+        }
+        true
+    }
+}
+
+impl<E: EntityRef> EntityRef for [E] {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        for e in self.iter_mut() {
+            e.for_each(action)
+        }
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        let mut kept = true;
+        for e in self.iter_mut() {
+            kept = e.retain(keep) && kept;
+        }
+        kept
+    }
+}
+
+impl<E: EntityRef, const N: usize> EntityRef for [E; N] {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        self[..].for_each(action)
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        self[..].retain(keep)
+    }
+}
+
+impl<E: EntityRef> EntityRef for Vec<E> {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        self[..].for_each(action)
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        let elements = std::mem::take(self);
+        for mut e in elements {
+            if e.retain(keep) {
+                self.push(e);
+            }
+        }
+        true
+    }
+}
+
+impl<E: EntityRef> EntityRef for VecDeque<E> {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        for e in self.iter_mut() {
+            e.for_each(action)
+        }
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        let elements = std::mem::take(self);
+        for mut e in elements {
+            if e.retain(keep) {
+                self.push_back(e);
+            }
+        }
+        true
+    }
+}
+
+impl<K, E: EntityRef, S> EntityRef for HashMap<K, E, S> {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        for e in self.values_mut() {
+            e.for_each(action)
+        }
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        self.retain(|_k, e| e.retain(keep));
+        true
+    }
+}
+
+impl<K: Ord, E: EntityRef> EntityRef for BTreeMap<K, E> {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        for e in self.values_mut() {
+            e.for_each(action)
+        }
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        let elements = std::mem::take(self);
+        for (k, mut e) in elements {
+            if e.retain(keep) {
+                self.insert(k, e);
+            }
+        }
+        true
+    }
+}
+
+/// Elements of a set can't be modified in place, so the set is rebuilt.
+impl<E: EntityRef + Eq + Hash, S: BuildHasher> EntityRef for HashSet<E, S> {
+    fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+        let elements: Vec<E> = self.drain().collect();
+        for mut e in elements {
+            e.for_each(action);
+            self.insert(e);
+        }
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        let elements: Vec<E> = self.drain().collect();
+        for mut e in elements {
+            if e.retain(keep) {
+                self.insert(e);
+            }
+        }
+        true
+    }
 }
 
-impl<'e> EntityRef for &'e mut Vec<Entity> {
+impl<E: EntityRef + Ord> EntityRef for BTreeSet<E> {
     fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
-        self.iter_mut().for_each(action)
+        let elements = std::mem::take(self);
+        for mut e in elements {
+            e.for_each(action);
+            self.insert(e);
+        }
+    }
+
+    fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+        let elements = std::mem::take(self);
+        for mut e in elements {
+            if e.retain(keep) {
+                self.insert(e);
+            }
+        }
+        true
     }
 }
 
@@ -88,6 +322,15 @@ macro_rules! impl_entity_ref_tuple {
                 $($e1.for_each(action));
                 +;
             }
+
+            #[allow(non_snake_case)]
+            fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+                let ($e0, $($e1), +) = self;
+                let kept = $e0.retain(keep);
+                $(let kept = $e1.retain(keep) && kept);
+                +;
+                kept
+            }
         }
     };
 }
@@ -114,15 +357,15 @@ impl<'r, C: Component> Not for &'r mut WriteComps<'r, C> {
 impl<'r, C: Component, A: AccessOrder> Join<'r> for &'r ReadComps<'r, C, A> {
     type Element = C;
     type ElementFetcher = &'r ComponentStorage<C>;
-    type EntitiesIter = std::iter::Copied<std::slice::Iter<'r, Entity>>;
-    type ParEntitiesIter = rayon::iter::Copied<rayon::slice::Iter<'r, Entity>>;
+    type EntitiesIter = MatchedEntitiesIter<'r>;
+    type ParEntitiesIter = ParMatchedEntitiesIter<'r>;
 
     fn open(mut self) -> (Self::EntitiesIter, Self::ElementFetcher) {
-        (self.storage.entity_iter(), self.elem_fetcher())
+        (self.matched_entities_iter(), self.elem_fetcher())
     }
 
     fn par_open(mut self) -> (Self::ParEntitiesIter, Self::ElementFetcher) {
-        (self.storage.par_entity_iter(), self.elem_fetcher())
+        (self.par_matched_entities_iter(), self.elem_fetcher())
     }
 
     fn entities(&self) -> &'r Entities {
@@ -138,11 +381,11 @@ impl<'r, C: Component, A: AccessOrder> Join<'r> for &'r ReadComps<'r, C, A> {
     }
 
     fn matched_entities_iter(&self) -> Self::EntitiesIter {
-        self.storage.entity_iter()
+        MatchedEntitiesIter::get::<Self>(self.entities.read())
     }
 
     fn par_matched_entities_iter(&self) -> Self::ParEntitiesIter {
-        self.storage.par_entity_iter()
+        ParMatchedEntitiesIter::get::<Self>(self.entities.read())
     }
 
     fn fill_matcher(matcher: &mut ArchetypeMatcher) {
@@ -153,8 +396,8 @@ impl<'r, C: Component, A: AccessOrder> Join<'r> for &'r ReadComps<'r, C, A> {
 impl<'r, C: Component> Join<'r> for &'r mut WriteComps<'r, C> {
     type Element = C;
     type ElementFetcher = &'r mut ComponentStorage<C>;
-    type EntitiesIter = std::iter::Copied<std::slice::Iter<'r, Entity>>;
-    type ParEntitiesIter = rayon::iter::Copied<rayon::slice::Iter<'r, Entity>>;
+    type EntitiesIter = MatchedEntitiesIter<'r>;
+    type ParEntitiesIter = ParMatchedEntitiesIter<'r>;
 
     fn open(self) -> (Self::EntitiesIter, Self::ElementFetcher) {
         let storage = unsafe { &mut *(&mut self.storage as *mut _ as *mut _) };
@@ -180,13 +423,11 @@ impl<'r, C: Component> Join<'r> for &'r mut WriteComps<'r, C> {
     }
 
     fn matched_entities_iter(&self) -> Self::EntitiesIter {
-        let components: &'r WriteComps<'r, C> = unsafe { &*(self as *const &mut _ as *const _) };
-        components.storage.entity_iter()
+        MatchedEntitiesIter::get::<Self>(self.entities.read())
     }
 
     fn par_matched_entities_iter(&self) -> Self::ParEntitiesIter {
-        let components: &'r WriteComps<'r, C> = unsafe { &*(self as *const &mut _ as *const _) };
-        components.storage.par_entity_iter()
+        ParMatchedEntitiesIter::get::<Self>(self.entities.read())
     }
 
     fn fill_matcher(matcher: &mut ArchetypeMatcher) {
@@ -202,6 +443,10 @@ impl<'r, C: Component, A: AccessOrder> ReadComps<'r, C, A> {
             _phantom: Default::default(),
         }
     }
+    /// see `ComponentStorage::find_by`
+    pub fn find_by<V: Serialize + ?Sized>(&self, field: &str, value: &V) -> Vec<Entity> {
+        self.storage.find_by(field, value)
+    }
 }
 
 impl<'r, C: Component> WriteComps<'r, C> {
@@ -212,10 +457,19 @@ impl<'r, C: Component> WriteComps<'r, C> {
             _phantom: Default::default(),
         }
     }
+    /// The entity is matched by joins with the component after the next sync point
     pub fn insert(&mut self, entity: Entity, component: C) {
         self.storage.insert(entity, component);
         self.entities.on_component_inserted::<C>(entity);
     }
+    pub fn remove(&mut self, entity: Entity) {
+        self.storage.remove(entity);
+        self.entities.on_component_removed::<C>(entity);
+    }
+    /// see `ComponentStorage::find_by`
+    pub fn find_by<V: Serialize + ?Sized>(&self, field: &str, value: &V) -> Vec<Entity> {
+        self.storage.find_by(field, value)
+    }
 }
 
 impl<'r, C: Component> SystemData<'r> for RBWComps<'r, C> {
@@ -285,6 +539,8 @@ impl World {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use tb_core::*;
     use tb_ecs_macro::*;
 
@@ -361,6 +617,82 @@ mod tests {
         }
     }
 
+    #[component]
+    enum EntityRefs {
+        Target(Entity),
+        Targets { targets: Vec<Option<Entity>> },
+    }
+
+    #[test]
+    fn entity_refs() {
+        let mut refs = EntityRefs::Targets {
+            targets: vec![Some(Entity::new(1)), None, Some(Entity::new(2))],
+        };
+        let mut count = 0;
+        refs.mut_entity_ref().for_each(&mut |entity: &mut Entity| {
+            *entity = Entity::new(3);
+            count += 1;
+        });
+        assert_eq!(count, 2);
+        match refs {
+            EntityRefs::Targets { targets } => assert_eq!(
+                targets,
+                vec![Some(Entity::new(3)), None, Some(Entity::new(3))]
+            ),
+            EntityRefs::Target(_) => unreachable!(),
+        }
+    }
+
+    type Target = Entity;
+
+    #[component]
+    struct TupleRefs(Entity, (Entity, f32), #[entity_ref] Target);
+
+    /// Generic components are not registered by `#[component]`
+    #[component]
+    struct GenericRefs<T> {
+        #[entity_ref]
+        value: T,
+        names: HashMap<Entity, String>,
+        #[entity_ref(skip)]
+        cached: Entity,
+    }
+
+    fn replace_refs(refs: &mut impl EntityRef, entity: Entity) -> usize {
+        let mut count = 0;
+        refs.for_each(&mut |e: &mut Entity| {
+            *e = entity;
+            count += 1;
+        });
+        count
+    }
+
+    #[test]
+    fn tuple_struct_entity_refs() {
+        let mut refs = TupleRefs(Entity::new(1), (Entity::new(2), 0.5), Entity::new(3));
+        assert_eq!(replace_refs(&mut refs, Entity::new(4)), 3);
+        assert_eq!(refs.0, Entity::new(4));
+        assert_eq!((refs.1).0, Entity::new(4));
+        assert_eq!((refs.1).1, 0.5);
+        assert_eq!(refs.2, Entity::new(4));
+    }
+
+    #[test]
+    fn generic_entity_refs() {
+        let mut names = HashMap::new();
+        names.insert(Entity::new(1), "one".to_owned());
+        let mut refs = GenericRefs {
+            value: Some(Entity::new(2)),
+            names,
+            cached: Entity::new(3),
+        };
+        assert_eq!(replace_refs(&mut refs, Entity::new(4)), 1);
+        assert_eq!(refs.value, Some(Entity::new(4)));
+        // map keys are not references
+        assert!(refs.names.contains_key(&Entity::new(1)));
+        assert_eq!(refs.cached, Entity::new(3));
+    }
+
     #[test]
     fn write_components_open() {
         let mut world = World::default();
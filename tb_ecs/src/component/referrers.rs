@@ -0,0 +1,68 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::Entity;
+
+/// The owners of the components referencing an entity in the fields with a kill policy.
+/// Built on the first lookup, changes are recorded and applied on the next one.
+#[derive(Default)]
+pub(crate) struct Referrers {
+    built: bool,
+    dirty: HashSet<Entity>,
+    owners: HashMap<Entity, HashSet<Entity>>,
+    refs: HashMap<Entity, Vec<Entity>>,
+}
+
+impl Referrers {
+    pub(crate) fn on_changed(&mut self, owner: Entity) {
+        if self.built {
+            self.dirty.insert(owner);
+        }
+    }
+
+    pub(crate) fn on_all_changed(&mut self) {
+        self.built = false;
+        self.dirty.clear();
+        self.owners.clear();
+        self.refs.clear();
+    }
+
+    pub(crate) fn is_built(&self) -> bool {
+        self.built
+    }
+
+    pub(crate) fn set_built(&mut self) {
+        self.built = true;
+    }
+
+    pub(crate) fn take_dirty(&mut self) -> HashSet<Entity> {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Replace the references of `owner`
+    pub(crate) fn set_refs(&mut self, owner: Entity, refs: Vec<Entity>) {
+        if let Some(old_refs) = self.refs.remove(&owner) {
+            for target in old_refs {
+                if let Some(owners) = self.owners.get_mut(&target) {
+                    owners.remove(&owner);
+                    if owners.is_empty() {
+                        self.owners.remove(&target);
+                    }
+                }
+            }
+        }
+        if refs.is_empty() {
+            return;
+        }
+        for &target in &refs {
+            self.owners.entry(target).or_default().insert(owner);
+        }
+        self.refs.insert(owner, refs);
+    }
+
+    pub(crate) fn owners_of(&self, target: Entity) -> Vec<Entity> {
+        self.owners
+            .get(&target)
+            .map(|owners| owners.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,282 @@
+use std::marker::PhantomData;
+
+use errors::*;
+use tb_core::serde::de::DeserializeOwned;
+use tb_core::serde_json::{self, Value};
+use tb_core::*;
+
+use crate::registry::ComponentRegistry;
+use crate::{Entity, World};
+
+mod errors {
+    pub use tb_core::error::*;
+
+    error_chain! {
+        foreign_links {
+            Json(tb_core::serde_json::Error);
+        }
+
+        errors {
+            UnknownField(path: String) {
+                description("Unknown field"),
+                display("Unknown field. path: {}", path),
+            }
+        }
+    }
+}
+
+pub type ReflectResult<T> = Result<T>;
+pub type ReflectError = Error;
+
+/// Access the fields of a value by name.
+/// A path is the field names separated by `.`, elements of sequences are named by their indices,
+/// and the empty path is the value itself.
+pub trait Reflect {
+    fn fields() -> Vec<FieldInfo>
+    where
+        Self: Sized;
+
+    fn reflect_type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn reflect_fields(&self) -> Vec<FieldInfo>;
+
+    fn get_field(&self, path: &str) -> ReflectResult<Value>;
+
+    fn set_field(&mut self, path: &str, value: Value) -> ReflectResult<()>;
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldInfo {
+    name: &'static str,
+    type_name: &'static str,
+    fields: Vec<FieldInfo>,
+}
+
+impl FieldInfo {
+    pub fn new<T>(name: &'static str, fields: Vec<FieldInfo>) -> Self {
+        Self {
+            name,
+            type_name: std::any::type_name::<T>(),
+            fields,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Fields of the field, if its type is reflected
+    pub fn fields(&self) -> &[FieldInfo] {
+        &self.fields
+    }
+}
+
+/// Select the nested fields of `T` by whether it is reflected,
+/// `(&ReflectProbe::<T>::new()).nested_fields()` is empty for other types.
+pub struct ReflectProbe<T>(PhantomData<T>);
+
+impl<T> ReflectProbe<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Default for ReflectProbe<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait ReflectNestedFields {
+    fn nested_fields(&self) -> Vec<FieldInfo>;
+}
+
+impl<T: Reflect> ReflectNestedFields for ReflectProbe<T> {
+    fn nested_fields(&self) -> Vec<FieldInfo> {
+        T::fields()
+    }
+}
+
+pub trait ReflectNoNestedFields {
+    fn nested_fields(&self) -> Vec<FieldInfo>;
+}
+
+impl<T> ReflectNoNestedFields for &ReflectProbe<T> {
+    fn nested_fields(&self) -> Vec<FieldInfo> {
+        vec![]
+    }
+}
+
+/// Split the first field name from the rest of the path
+pub fn split_field_path(path: &str) -> (&str, &str) {
+    match path.find('.') {
+        Some(dot) => (&path[..dot], &path[dot + 1..]),
+        None => (path, ""),
+    }
+}
+
+/// Read the field at `path` of a value through its serialization
+pub fn reflect_get<T: Serialize>(value: &T, path: &str) -> ReflectResult<Value> {
+    let value = serde_json::to_value(value)?;
+    json_path(&value, path)
+        .cloned()
+        .ok_or_else(|| unknown_field_error(path))
+}
+
+/// Write the field at `path` of a value through its serialization
+pub fn reflect_set<T: Serialize + DeserializeOwned>(
+    value: &mut T,
+    path: &str,
+    field: Value,
+) -> ReflectResult<()> {
+    if path.is_empty() {
+        *value = serde_json::from_value(field)?;
+        return Ok(());
+    }
+    let mut serialized = serde_json::to_value(&*value)?;
+    *json_path_mut(&mut serialized, path).ok_or_else(|| unknown_field_error(path))? = field;
+    *value = serde_json::from_value(serialized)?;
+    Ok(())
+}
+
+fn json_path<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, name| match value {
+        Value::Object(object) => object.get(name),
+        Value::Array(array) => array.get(name.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+fn json_path_mut<'v>(value: &'v mut Value, path: &str) -> Option<&'v mut Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, name| match value {
+        Value::Object(object) => object.get_mut(name),
+        Value::Array(array) => array.get_mut(name.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+pub fn unknown_field_error(path: &str) -> ReflectError {
+    ErrorKind::UnknownField(path.to_owned()).into()
+}
+
+macro_rules! impl_reflect_xyz {
+    ($($ty:ty: $field_ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn fields() -> Vec<FieldInfo> {
+                    vec![
+                        FieldInfo::new::<$field_ty>("x", vec![]),
+                        FieldInfo::new::<$field_ty>("y", vec![]),
+                        FieldInfo::new::<$field_ty>("z", vec![]),
+                    ]
+                }
+
+                fn reflect_fields(&self) -> Vec<FieldInfo> {
+                    Self::fields()
+                }
+
+                fn get_field(&self, path: &str) -> ReflectResult<Value> {
+                    match split_field_path(path).0 {
+                        "" | "x" | "y" | "z" => reflect_get(self, path),
+                        _ => Err(unknown_field_error(path)),
+                    }
+                }
+
+                fn set_field(&mut self, path: &str, value: Value) -> ReflectResult<()> {
+                    match split_field_path(path).0 {
+                        "" | "x" | "y" | "z" => reflect_set(self, path, value),
+                        _ => Err(unknown_field_error(path)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_xyz!(Point3: NumType, Vector3: NumType, Euler: Deg);
+
+impl World {
+    /// Reflect the component of `entity` by the stable name of the component type
+    pub fn reflect_component(&mut self, entity: Entity, name: &str) -> Option<&mut dyn Reflect> {
+        let info = ComponentRegistry::info_by_name(name)?;
+        unsafe { info.reflect(self, entity) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tb_core::serde_json::json;
+
+    use crate::*;
+
+    #[component(name = "test.Transform")]
+    struct Transform {
+        name: String,
+        location: Point3,
+        rotation: Euler,
+        scales: Vec<f32>,
+    }
+
+    #[test]
+    fn fields() {
+        let fields = Transform::fields();
+        let names: Vec<_> = fields.iter().map(|field| field.name()).collect();
+        assert_eq!(names, vec!["name", "location", "rotation", "scales"]);
+        assert_eq!(fields[0].type_name(), std::any::type_name::<String>());
+        assert!(fields[0].fields().is_empty());
+        let location_fields: Vec<_> = fields[1].fields().iter().map(|f| f.name()).collect();
+        assert_eq!(location_fields, vec!["x", "y", "z"]);
+        assert_eq!(
+            fields[2].fields()[0].type_name(),
+            std::any::type_name::<Deg>()
+        );
+    }
+
+    #[test]
+    fn get_and_set() {
+        let mut world = World::default();
+        let entity = world
+            .create_entity()
+            .with(Transform {
+                name: "a".to_owned(),
+                location: Point3::new(1.0, 2.0, 3.0),
+                rotation: Euler::new(0.0, 90.0, 0.0),
+                scales: vec![1.0, 2.0],
+            })
+            .create();
+
+        let transform = world.reflect_component(entity, "test.Transform").unwrap();
+        assert_eq!(transform.get_field("name").unwrap(), json!("a"));
+        assert_eq!(transform.get_field("location.y").unwrap(), json!(2.0));
+        assert_eq!(transform.get_field("rotation.y").unwrap(), json!(90.0));
+        assert_eq!(transform.get_field("scales.1").unwrap(), json!(2.0));
+        assert!(transform.get_field("location.w").is_err());
+        assert!(transform.get_field("unknown").is_err());
+
+        transform.set_field("name", json!("b")).unwrap();
+        transform.set_field("location.x", json!(5.0)).unwrap();
+        transform
+            .set_field("rotation", json!({ "x": 10.0, "y": 0.0, "z": 0.0 }))
+            .unwrap();
+        assert!(transform.set_field("location.x", json!("x")).is_err());
+
+        let transform = unsafe { world.fetch_components::<Transform>() }
+            .fetch(entity)
+            .unwrap();
+        assert_eq!(transform.name, "b");
+        assert_eq!(transform.location, Point3::new(5.0, 2.0, 3.0));
+        assert_eq!(transform.rotation, Euler::new(10.0, 0.0, 0.0));
+    }
+}
@@ -1,12 +1,33 @@
-use std::any::TypeId;
-use std::collections::hash_map::Entry;
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::lazy::SyncLazy;
 use std::marker::PhantomData;
 use std::ops::{Deref, Index};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::{Component, Entity, World};
+use errors::*;
+use tb_core::serde::de::DeserializeOwned;
+use tb_core::serde::Serialize;
+use tb_core::serde_json::{self, Value};
+
+use crate::{
+    CleanDanglingEntityRef, Component, ComponentStorage, DanglingAction, DynamicComponentInfo,
+    DynamicOperation, Entities, Entity, EntityRef, FieldInfo, LocalToWorldLink, Reflect, World,
+};
+
+mod errors {
+    pub use tb_core::error::*;
+
+    error_chain! {
+        errors {
+            NameCollision(name: String) {
+                description("Component name is registered by another component"),
+                display("Component name is registered by another component. name: {}", name),
+            }
+        }
+    }
+}
 
 #[derive(Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub struct ComponentIndex(usize);
@@ -18,13 +39,26 @@ impl From<usize> for ComponentIndex {
 }
 
 impl ComponentIndex {
+    /// Cached per component type, the registry is only locked on the first call
+    /// and after a registered type is replaced.
     pub fn get<C: Component>() -> Self {
+        match C::index_cache() {
+            Some(cache) => cache.get_or_init(Self::lookup::<C>),
+            None => Self::lookup::<C>(),
+        }
+    }
+
+    fn lookup<C: Component>() -> Self {
         let registry = ComponentRegistry::read();
         *registry
             .type_id_to_index
             .get(&ComponentTypeId::new::<C>())
             .unwrap()
     }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        ComponentRegistry::read().name_to_index.get(name).copied()
+    }
 }
 
 impl Deref for ComponentIndex {
@@ -35,51 +69,278 @@ impl Deref for ComponentIndex {
     }
 }
 
-impl Index<ComponentIndex> for Vec<&ComponentInfo> {
+impl Index<ComponentIndex> for Vec<Option<&ComponentInfo>> {
     type Output = ComponentInfo;
 
     fn index(&self, index: ComponentIndex) -> &Self::Output {
-        self[index.0]
+        self[index.0].expect("Component is removed")
+    }
+}
+
+/// Bumped when the index of a registered type may change, which invalidates the caches
+static INDEX_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The index of a component type with the generation it is looked up in,
+/// packed into one atomic as `generation << 32 | (index + 1)`, 0 if empty.
+pub struct ComponentIndexCache(AtomicU64);
+
+impl ComponentIndexCache {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    fn get_or_init(&self, lookup: impl FnOnce() -> ComponentIndex) -> ComponentIndex {
+        let generation = INDEX_GENERATION.load(Ordering::Acquire);
+        let cached = self.0.load(Ordering::Acquire);
+        if cached != 0 && cached >> 32 == generation {
+            return ComponentIndex((cached & 0xffff_ffff) as usize - 1);
+        }
+        let index = lookup();
+        self.0
+            .store(generation << 32 | (*index as u64 + 1), Ordering::Release);
+        index
     }
 }
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone)]
-struct ComponentTypeId(TypeId);
+enum ComponentTypeId {
+    Type(TypeId),
+    Dynamic(ComponentIndex),
+}
 
 impl ComponentTypeId {
     fn new<C: Component>() -> Self {
-        Self(TypeId::of::<C>())
+        Self::Type(TypeId::of::<C>())
     }
 }
 
 pub struct ComponentRegistry {
-    infos: Vec<&'static ComponentInfo>,
+    infos: Vec<Option<&'static ComponentInfo>>,
+    owners: Vec<Option<String>>,
     type_id_to_index: HashMap<ComponentTypeId, ComponentIndex>,
+    name_to_index: HashMap<String, ComponentIndex>,
+    /// The indices of removed components, reused when the name is registered again
+    removed_name_to_index: HashMap<String, ComponentIndex>,
 }
 
 impl ComponentRegistry {
-    pub fn add_component_infos(component_infos: Box<dyn Iterator<Item = &'static ComponentInfo>>) {
-        let cr: &mut ComponentRegistry = &mut Self::write();
-        let infos = &mut cr.infos;
-        let type_id_to_index = &mut cr.type_id_to_index;
+    /// Register the component infos of `owner`, e.g. a plugin.
+    /// An info replaces the registered one with the same name if both are registered by
+    /// the same owner, e.g. when a plugin is reloaded. The same type registered by the engine
+    /// or another owner is shared, and stays theirs. Otherwise the name collides,
+    /// and nothing is registered.
+    pub fn add_component_infos(
+        owner: &str,
+        component_infos: Box<dyn Iterator<Item = &'static ComponentInfo>>,
+    ) -> Result<()> {
+        let component_infos: Vec<_> = component_infos.collect();
+        let mut cr = Self::write();
+        cr.check_collisions(Some(owner), &component_infos)?;
         for info in component_infos {
-            match type_id_to_index.entry(info.type_id) {
-                Entry::Occupied(occupied) => {
-                    let index = unsafe { infos.get_unchecked_mut(**occupied.get()) };
-                    *index = info;
+            if !cr.is_owned_by_other(owner, info) {
+                cr.insert(Some(owner), info);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the same type is registered under the name by the engine or another owner
+    fn is_owned_by_other(&self, owner: &str, info: &ComponentInfo) -> bool {
+        match self.name_to_index.get(info.name()) {
+            Some(&index) => {
+                self.owners[*index].as_deref() != Some(owner)
+                    && self.infos[*index]
+                        .map_or(false, |registered| registered.type_id == info.type_id)
+            }
+            None => false,
+        }
+    }
+
+    /// The indices of the components registered by `owner`
+    pub fn indices_of_owner(owner: &str) -> Vec<ComponentIndex> {
+        Self::read().owned_by(owner)
+    }
+
+    fn owned_by(&self, owner: &str) -> Vec<ComponentIndex> {
+        self.owners
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| o.as_deref() == Some(owner))
+            .map(|(index, _)| ComponentIndex(index))
+            .collect()
+    }
+
+    /// Unregister the components of `owner`, e.g. before the plugin is unloaded.
+    /// Their storages should be removed from the worlds first by `World::remove_component_types`.
+    pub fn remove_owner(owner: &str) {
+        let mut cr = Self::write();
+        for index in cr.owned_by(owner) {
+            let info = cr.infos[*index].take().unwrap();
+            cr.owners[*index] = None;
+            cr.type_id_to_index.remove(&info.type_id);
+            cr.name_to_index.remove(info.name());
+            cr.removed_name_to_index.insert(info.name.clone(), index);
+        }
+    }
+
+    fn check_collisions(
+        &self,
+        owner: Option<&str>,
+        infos: &[&'static ComponentInfo],
+    ) -> Result<()> {
+        let mut names = HashMap::with_capacity(infos.len());
+        for info in infos {
+            if let Some(other) = names.insert(info.name(), info.type_id) {
+                if other != info.type_id {
+                    bail!(ErrorKind::NameCollision(info.name.clone()));
                 }
-                Entry::Vacant(vacant) => {
-                    vacant.insert(ComponentIndex(infos.len()));
-                    infos.push(info);
+            }
+            if let Some(&index) = self.name_to_index.get(info.name()) {
+                let same_type = self.infos[index].type_id == info.type_id;
+                let same_owner = owner.is_some() && self.owners[*index].as_deref() == owner;
+                if !same_type && !same_owner {
+                    bail!(ErrorKind::NameCollision(info.name.clone()));
                 }
             }
         }
+        Ok(())
+    }
+
+    fn insert(&mut self, owner: Option<&str>, info: &'static ComponentInfo) -> ComponentIndex {
+        match self.name_to_index.get(info.name()).copied() {
+            Some(index) => {
+                let replaced = self.infos[*index].replace(info).unwrap();
+                self.type_id_to_index.remove(&replaced.type_id);
+                self.type_id_to_index.insert(info.type_id, index);
+                INDEX_GENERATION.fetch_add(1, Ordering::AcqRel);
+                self.owners[*index] = owner.map(str::to_owned);
+                index
+            }
+            None => {
+                let index = match self.removed_name_to_index.remove(info.name()) {
+                    Some(index) => {
+                        self.infos[*index] = Some(info);
+                        self.owners[*index] = owner.map(str::to_owned);
+                        index
+                    }
+                    None => {
+                        self.infos.push(Some(info));
+                        self.owners.push(owner.map(str::to_owned));
+                        ComponentIndex(self.infos.len() - 1)
+                    }
+                };
+                self.type_id_to_index.insert(info.type_id, index);
+                self.name_to_index.insert(info.name.clone(), index);
+                index
+            }
+        }
+    }
+
+    /// Register a component type defined at runtime.
+    /// If the name is registered already, the existing info is returned.
+    pub(crate) fn register_dynamic(
+        name: &str,
+        create: impl FnOnce(ComponentIndex) -> DynamicComponentInfo,
+    ) -> &'static ComponentInfo {
+        let mut cr = Self::write();
+        if let Some(&index) = cr.name_to_index.get(name) {
+            return cr.infos[*index].unwrap();
+        }
+        let index = cr
+            .removed_name_to_index
+            .get(name)
+            .copied()
+            .unwrap_or(ComponentIndex(cr.infos.len()));
+        let info: &'static ComponentInfo = Box::leak(Box::new(ComponentInfo::new_dynamic(
+            name,
+            index,
+            create(index),
+        )));
+        cr.insert(None, info);
+        info
+    }
+
+    pub fn info(component_index: ComponentIndex) -> Option<&'static ComponentInfo> {
+        Self::read().infos.get(*component_index).copied().flatten()
+    }
+
+    /// Find the info of a component by its stable name
+    pub fn info_by_name(name: &str) -> Option<&'static ComponentInfo> {
+        let cr = Self::read();
+        cr.name_to_index
+            .get(name)
+            .map(|&index| cr.infos[*index].unwrap())
+    }
+
+    /// Find the info of a component by its stable name,
+    /// or by the type name if only one component has it.
+    pub fn info_by_name_or_type_name(name: &str) -> Option<&'static ComponentInfo> {
+        let cr = Self::read();
+        if let Some(&index) = cr.name_to_index.get(name) {
+            return cr.infos[*index];
+        }
+        let suffix = format!("::{}", name);
+        let mut found = cr
+            .infos
+            .iter()
+            .flatten()
+            .filter(|info| info.name.ends_with(&suffix));
+        match (found.next(), found.next()) {
+            (Some(&info), None) => Some(info),
+            _ => None,
+        }
     }
 
     pub fn for_each(op: impl FnMut(&&ComponentInfo)) {
         let this = Self::read();
         let this: &Self = &this;
-        this.infos.iter().for_each(op);
+        this.infos.iter().flatten().for_each(op);
+    }
+
+    /// Apply the kill policies of every component type to the references of `killed`,
+    /// only the components referencing it are visited.
+    /// The owners which should be killed in cascade are pushed into `owners_to_kill`.
+    ///
+    /// # Safety
+    ///
+    /// No one else can access the component storages at the same time.
+    pub(crate) unsafe fn clean_dangling_entity_refs(
+        world: &World,
+        killed: Entity,
+        owners_to_kill: &mut Vec<Entity>,
+    ) {
+        let cleans: Vec<CleanDanglingFn> = Self::read()
+            .infos
+            .iter()
+            .flatten()
+            .filter_map(|info| info.clean_dangling_entity_refs)
+            .collect();
+        for clean in cleans {
+            clean(world, killed, owners_to_kill);
+        }
+    }
+
+    /// Replace the references to the keys of `map` in the component of `entity`
+    ///
+    /// # Safety
+    ///
+    /// No one else can access the component storage at the same time.
+    pub(crate) unsafe fn remap_entity_refs(
+        world: &World,
+        entity: Entity,
+        component_index: ComponentIndex,
+        map: &HashMap<Entity, Entity>,
+    ) {
+        let info = match Self::info(component_index) {
+            Some(info) => info,
+            None => return,
+        };
+        if let Some(remap) = info.remap_entity_refs {
+            remap(world, entity, map);
+        } else if let Some(dynamic) = info.dynamic() {
+            dynamic.remap_entity_refs(world, entity, map);
+        }
     }
 
     pub(crate) fn operation(
@@ -97,14 +358,18 @@ impl ComponentRegistry {
         static INSTANCE: SyncLazy<RwLock<ComponentRegistry>> = SyncLazy::new(|| {
             let mut instance = ComponentRegistry {
                 infos: vec![],
+                owners: vec![],
                 type_id_to_index: Default::default(),
+                name_to_index: Default::default(),
+                removed_name_to_index: Default::default(),
             };
 
-            for info in inventory::iter::<ComponentInfo> {
-                instance
-                    .type_id_to_index
-                    .insert(info.type_id, ComponentIndex(instance.infos.len()));
-                instance.infos.push(info);
+            let infos: Vec<_> = inventory::iter::<ComponentInfo>.into_iter().collect();
+            if let Err(e) = instance.check_collisions(None, &infos) {
+                panic!("{}", e);
+            }
+            for info in infos {
+                instance.insert(None, info);
             }
             RwLock::new(instance)
         });
@@ -122,6 +387,12 @@ impl ComponentRegistry {
 
 pub trait ComponentOperation: Send + Sync {
     unsafe fn remove_from_world(&self, world: &World, entity: Entity);
+    /// Clone the component of `from` into the storage for `to`, archetypes are not updated.
+    unsafe fn clone_component(&self, world: &World, from: Entity, to: Entity);
+    /// Move the components of the linked entities from `from` into `to`, archetypes are not updated.
+    unsafe fn move_components(&self, from: &World, to: &mut World, link: &LocalToWorldLink);
+    /// Drop the storage of the component, archetypes are not updated.
+    fn remove_storage(&self, world: &mut World);
 }
 
 struct Operation<C: Component> {
@@ -132,26 +403,313 @@ unsafe impl<C: Component> Send for Operation<C> {}
 
 unsafe impl<C: Component> Sync for Operation<C> {}
 
-impl<C: Component> ComponentOperation for Operation<C> {
+impl<C: Component + Clone> ComponentOperation for Operation<C> {
     unsafe fn remove_from_world(&self, world: &World, entity: Entity) {
         world.fetch_components_mut::<C>().remove(entity)
     }
+
+    unsafe fn clone_component(&self, world: &World, from: Entity, to: Entity) {
+        let storage = world.fetch_components_mut::<C>();
+        if let Some(component) = storage.fetch(from).cloned() {
+            storage.insert(to, component);
+        }
+    }
+
+    unsafe fn move_components(&self, from: &World, to: &mut World, link: &LocalToWorldLink) {
+        let from = match from.try_fetch_mut::<ComponentStorage<C>>() {
+            Ok(from) => from,
+            Err(_) => {
+                return;
+            }
+        };
+        let to = to.insert_components::<C>();
+        for (local, component) in from.drain() {
+            if let Some(entity) = link.get(local) {
+                to.insert(entity, component);
+            }
+        }
+    }
+
+    fn remove_storage(&self, world: &mut World) {
+        world.remove::<ComponentStorage<C>>();
+    }
+}
+
+type RemapFn = unsafe fn(&World, Entity, &HashMap<Entity, Entity>);
+
+unsafe fn remap_entity_refs<C: Component + EntityRef>(
+    world: &World,
+    entity: Entity,
+    map: &HashMap<Entity, Entity>,
+) {
+    if let Some(component) = world.fetch_components_mut::<C>().fetch_mut(entity) {
+        component.for_each(&mut |e: &mut Entity| {
+            if let Some(&mapped) = map.get(e) {
+                *e = mapped;
+            }
+        });
+    }
+}
+
+type CleanDanglingFn = unsafe fn(&World, Entity, &mut Vec<Entity>);
+
+unsafe fn clean_dangling_entity_refs<C: CleanDanglingEntityRef>(
+    world: &World,
+    killed: Entity,
+    owners_to_kill: &mut Vec<Entity>,
+) {
+    let storage = match world.try_fetch_mut::<ComponentStorage<C>>() {
+        Ok(storage) => storage,
+        Err(_) => {
+            return;
+        }
+    };
+    let mut removed = vec![];
+    for owner in storage.referrers(killed) {
+        let component = match storage.fetch_mut(owner) {
+            Some(component) => component,
+            None => continue,
+        };
+        match component.on_entity_killed(killed) {
+            DanglingAction::Keep => {}
+            DanglingAction::RemoveComponent => removed.push(owner),
+            DanglingAction::KillOwner => owners_to_kill.push(owner),
+        }
+    }
+    if removed.is_empty() {
+        return;
+    }
+    let entities = world.fetch::<Entities>();
+    for owner in removed {
+        storage.remove(owner);
+        entities.on_component_removed::<C>(owner);
+    }
+}
+
+type ReflectFn = for<'w> unsafe fn(&'w World, Entity) -> Option<&'w mut dyn Reflect>;
+
+unsafe fn reflect_component<C: Component + Reflect>(
+    world: &World,
+    entity: Entity,
+) -> Option<&mut dyn Reflect> {
+    let storage = world.try_fetch_mut::<ComponentStorage<C>>().ok()?;
+    storage
+        .fetch_mut(entity)
+        .map(|component| component as &mut dyn Reflect)
+}
+
+type FromJsonFn = fn(Value) -> serde_json::Result<Box<dyn Any + Send>>;
+
+fn component_from_json<C: Component + DeserializeOwned>(
+    value: Value,
+) -> serde_json::Result<Box<dyn Any + Send>> {
+    serde_json::from_value::<C>(value).map(|component| Box::new(component) as Box<dyn Any + Send>)
+}
+
+type InsertAnyFn = fn(&mut World, Entity, Box<dyn Any + Send>);
+
+fn insert_any_component<C: Component>(
+    world: &mut World,
+    entity: Entity,
+    component: Box<dyn Any + Send>,
+) {
+    if let Ok(component) = component.downcast::<C>() {
+        world.insert_components::<C>().insert(entity, *component);
+    }
+}
+
+type SaveStorageFn = fn(&World) -> Option<serde_json::Result<Value>>;
+
+fn save_storage<C: Component + Serialize>(world: &World) -> Option<serde_json::Result<Value>> {
+    let storage = unsafe { world.try_fetch::<ComponentStorage<C>>() }.ok()?;
+    Some(serde_json::to_value(storage))
+}
+
+/// The entities whose components were inserted or removed by restoring a storage
+#[derive(Default)]
+pub(crate) struct RestoredStorage {
+    pub(crate) inserted: Vec<Entity>,
+    pub(crate) removed: Vec<Entity>,
+}
+
+type RestoreStorageFn = fn(&mut World, Value) -> serde_json::Result<RestoredStorage>;
+
+fn restore_storage<C: Component + DeserializeOwned>(
+    world: &mut World,
+    value: Value,
+) -> serde_json::Result<RestoredStorage> {
+    let mut saved: ComponentStorage<C> = serde_json::from_value(value)?;
+    let storage = world.insert_components::<C>();
+    let mut restored = RestoredStorage::default();
+    restored.removed = storage
+        .open()
+        .0
+        .copied()
+        .filter(|&entity| !saved.contains(entity))
+        .collect();
+    for &entity in &restored.removed {
+        storage.remove(entity);
+    }
+    for (entity, component) in saved.drain() {
+        if !storage.contains(entity) {
+            restored.inserted.push(entity);
+        }
+        storage.insert(entity, component);
+    }
+    Ok(restored)
 }
 
 pub struct ComponentInfo {
     type_id: ComponentTypeId,
+    name: String,
     operation: Box<dyn ComponentOperation>,
+    remap_entity_refs: Option<RemapFn>,
+    clean_dangling_entity_refs: Option<CleanDanglingFn>,
+    reflect_fields: Option<fn() -> Vec<FieldInfo>>,
+    reflect: Option<ReflectFn>,
+    from_json: Option<(FromJsonFn, InsertAnyFn)>,
+    storage_json: Option<(SaveStorageFn, RestoreStorageFn)>,
+    dynamic: Option<DynamicComponentInfo>,
 }
 
 impl ComponentInfo {
-    pub fn new<C: Component>() -> Self {
+    /// The name defaults to the type name, use `with_name` for a name stable across builds
+    pub fn new<C: Component + Clone>() -> Self {
         Self {
             type_id: ComponentTypeId::new::<C>(),
+            name: std::any::type_name::<C>().to_owned(),
             operation: Box::new(Operation::<C> {
                 _phantom: Default::default(),
             }),
+            remap_entity_refs: None,
+            clean_dangling_entity_refs: None,
+            reflect_fields: None,
+            reflect: None,
+            from_json: None,
+            storage_json: None,
+            dynamic: None,
+        }
+    }
+
+    fn new_dynamic(name: &str, index: ComponentIndex, dynamic: DynamicComponentInfo) -> Self {
+        Self {
+            type_id: ComponentTypeId::Dynamic(index),
+            name: name.to_owned(),
+            operation: Box::new(DynamicOperation::new(index)),
+            remap_entity_refs: None,
+            clean_dangling_entity_refs: None,
+            reflect_fields: None,
+            reflect: None,
+            from_json: None,
+            storage_json: None,
+            dynamic: Some(dynamic),
         }
     }
+
+    /// The fields of the component can be listed and accessed by name
+    pub fn with_reflect<C: Component + Reflect>(mut self) -> Self {
+        debug_assert!(self.type_id == ComponentTypeId::new::<C>());
+        self.reflect_fields = Some(C::fields);
+        self.reflect = Some(reflect_component::<C>);
+        self
+    }
+
+    /// The fields of the component, if it is reflected
+    pub fn fields(&self) -> Option<Vec<FieldInfo>> {
+        self.reflect_fields.map(|fields| fields())
+    }
+
+    /// # Safety
+    ///
+    /// No one else can access the component storage at the same time.
+    pub(crate) unsafe fn reflect<'w>(
+        &self,
+        world: &'w World,
+        entity: Entity,
+    ) -> Option<&'w mut dyn Reflect> {
+        self.reflect.and_then(|reflect| reflect(world, entity))
+    }
+
+    /// The component can be created from json by its name,
+    /// and its storage can be saved as json, e.g. across plugin reloads.
+    pub fn with_json<C: Component + Serialize + DeserializeOwned>(mut self) -> Self {
+        debug_assert!(self.type_id == ComponentTypeId::new::<C>());
+        self.from_json = Some((component_from_json::<C>, insert_any_component::<C>));
+        self.storage_json = Some((save_storage::<C>, restore_storage::<C>));
+        self
+    }
+
+    /// Deserialize the component, `None` if it can't be created from json
+    pub(crate) fn component_from_json(
+        &self,
+        value: Value,
+    ) -> Option<serde_json::Result<Box<dyn Any + Send>>> {
+        self.from_json.map(|(from_json, _)| from_json(value))
+    }
+
+    /// Insert a component created by `component_from_json` into its storage,
+    /// archetypes are not updated.
+    pub(crate) fn insert_any_component(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        component: Box<dyn Any + Send>,
+    ) {
+        if let Some((_, insert)) = self.from_json {
+            insert(world, entity, component);
+        }
+    }
+
+    /// Serialize the storage, `None` if it can't be saved as json,
+    /// `Some(None)` if the world has no storage of it
+    pub(crate) fn save_storage(&self, world: &World) -> Option<Option<serde_json::Result<Value>>> {
+        match &self.dynamic {
+            Some(dynamic) => Some(dynamic.save_storage(world)),
+            None => self.storage_json.map(|(save, _)| save(world)),
+        }
+    }
+
+    /// Replace the storage with one saved by `save_storage` in place, archetypes are not updated.
+    /// Returns the entities whose components were inserted or removed.
+    pub(crate) fn restore_storage(
+        &self,
+        world: &mut World,
+        value: Value,
+    ) -> Option<serde_json::Result<RestoredStorage>> {
+        match &self.dynamic {
+            Some(dynamic) => Some(dynamic.restore_storage(world, value)),
+            None => self.storage_json.map(|(_, restore)| restore(world, value)),
+        }
+    }
+
+    /// The name and layout of a component type registered at runtime
+    pub fn dynamic(&self) -> Option<&DynamicComponentInfo> {
+        self.dynamic.as_ref()
+    }
+
+    /// The name identifying the component in saved data and across plugin builds
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The component references entities, which are remapped when entities are cloned
+    pub fn with_entity_refs<C: Component + EntityRef>(mut self) -> Self {
+        debug_assert!(self.type_id == ComponentTypeId::new::<C>());
+        self.remap_entity_refs = Some(remap_entity_refs::<C>);
+        self
+    }
+
+    /// The entity references of the component declare kill policies
+    pub fn with_dangling_policy<C: CleanDanglingEntityRef>(mut self) -> Self {
+        debug_assert!(self.type_id == ComponentTypeId::new::<C>());
+        self.clean_dangling_entity_refs = Some(clean_dangling_entity_refs::<C>);
+        self
+    }
 }
 
 inventory::collect!(ComponentInfo);
@@ -169,6 +727,15 @@ mod tests {
     #[component]
     struct Component1;
 
+    #[component(name = "test.Named")]
+    struct Named;
+
+    /// Generic components are not registered by `#[component]`
+    #[component]
+    struct Removable<T> {
+        value: T,
+    }
+
     #[test]
     fn get_component_index() {
         let mut join_handles = vec![];
@@ -187,4 +754,102 @@ mod tests {
             join.join().unwrap()
         }
     }
+
+    #[test]
+    fn component_name() {
+        let name = concat!(module_path!(), "::Component0");
+        assert_eq!(
+            ComponentIndex::by_name(name),
+            Some(ComponentIndex::get::<Component0>())
+        );
+        assert_eq!(
+            ComponentIndex::by_name("test.Named"),
+            Some(ComponentIndex::get::<Named>())
+        );
+        assert_eq!(
+            ComponentRegistry::info_by_name("test.Named")
+                .unwrap()
+                .name(),
+            "test.Named"
+        );
+
+        let collided: &'static ComponentInfo =
+            Box::leak(Box::new(ComponentInfo::new::<Component1>().with_name(name)));
+        let infos = Box::new(std::iter::once(collided));
+        assert!(ComponentRegistry::add_component_infos("test", infos).is_err());
+        assert_eq!(
+            ComponentIndex::by_name(name),
+            Some(ComponentIndex::get::<Component0>())
+        );
+    }
+
+    #[test]
+    fn remove_owner() {
+        let info: &'static ComponentInfo = Box::leak(Box::new(
+            ComponentInfo::new::<Removable<i32>>().with_name("test.Removable"),
+        ));
+        ComponentRegistry::add_component_infos("test.owner", Box::new(std::iter::once(info)))
+            .unwrap();
+        let index = ComponentIndex::get::<Removable<i32>>();
+        assert_eq!(
+            ComponentRegistry::indices_of_owner("test.owner"),
+            vec![index]
+        );
+
+        let mut world = World::default();
+        let entity = world
+            .create_entity()
+            .with(Removable { value: 1 })
+            .with(Component0)
+            .create();
+        world.remove_component_types(&[index]);
+        ComponentRegistry::remove_owner("test.owner");
+        assert!(!world.contains::<ComponentStorage<Removable<i32>>>());
+        assert_eq!(ComponentIndex::by_name("test.Removable"), None);
+        assert!(ComponentRegistry::indices_of_owner("test.owner").is_empty());
+        assert_eq!(
+            unsafe { world.fetch::<Entities>() }.component_indices(entity),
+            vec![ComponentIndex::get::<Component0>()]
+        );
+
+        ComponentRegistry::add_component_infos("test.owner", Box::new(std::iter::once(info)))
+            .unwrap();
+        assert_eq!(ComponentIndex::by_name("test.Removable"), Some(index));
+    }
+
+    #[test]
+    fn shared_infos_stay_with_their_owner() {
+        let engine_index = ComponentIndex::get::<Component1>();
+        let engine_info = ComponentRegistry::info(engine_index).unwrap();
+        let shared: &'static ComponentInfo = Box::leak(Box::new(
+            ComponentInfo::new::<Component1>().with_name(engine_info.name()),
+        ));
+        let own: &'static ComponentInfo = Box::leak(Box::new(
+            ComponentInfo::new::<Removable<u8>>().with_name("test.Own"),
+        ));
+        ComponentRegistry::add_component_infos(
+            "test.first",
+            Box::new(vec![shared, own].into_iter()),
+        )
+        .unwrap();
+        ComponentRegistry::add_component_infos(
+            "test.second",
+            Box::new(vec![shared, own].into_iter()),
+        )
+        .unwrap();
+        let own_index = ComponentIndex::by_name("test.Own").unwrap();
+        assert_eq!(
+            ComponentRegistry::indices_of_owner("test.first"),
+            vec![own_index]
+        );
+        assert!(ComponentRegistry::indices_of_owner("test.second").is_empty());
+
+        ComponentRegistry::remove_owner("test.second");
+        ComponentRegistry::remove_owner("test.first");
+        assert!(std::ptr::eq(
+            ComponentRegistry::info(engine_index).unwrap(),
+            engine_info
+        ));
+        assert_eq!(ComponentIndex::by_name("test.Own"), None);
+    }
 }
@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use tb_core::serde_json;
+use tb_core::*;
+
+use crate::{Component, ComponentStorage, Entity};
+
+/// A value of an indexed field, serialized as json
+pub type IndexKey = String;
+
+pub fn index_key<T: Serialize + ?Sized>(value: &T) -> IndexKey {
+    serde_json::to_string(value).unwrap()
+}
+
+/// Indices from the values of the fields declared by `#[component(index = "...")]` to entities.
+/// Changes are recorded and applied on the next lookup.
+pub(crate) struct SecondaryIndices {
+    inner: Mutex<SecondaryIndicesInner>,
+}
+
+struct SecondaryIndicesInner {
+    all_dirty: bool,
+    dirty: HashSet<Entity>,
+    entries: HashMap<&'static str, HashMap<IndexKey, Vec<Entity>>>,
+    keys: HashMap<Entity, Vec<(&'static str, IndexKey)>>,
+}
+
+impl Default for SecondaryIndices {
+    /// Everything is rebuilt on the first lookup, e.g. after the storage is deserialized
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(SecondaryIndicesInner {
+                all_dirty: true,
+                dirty: Default::default(),
+                entries: Default::default(),
+                keys: Default::default(),
+            }),
+        }
+    }
+}
+
+impl SecondaryIndices {
+    pub(crate) fn on_changed(&mut self, entity: Entity) {
+        self.inner.get_mut().unwrap().dirty.insert(entity);
+    }
+
+    pub(crate) fn on_all_changed(&mut self) {
+        self.inner.get_mut().unwrap().all_dirty = true;
+    }
+
+    pub(crate) fn find<C: Component>(
+        &self,
+        storage: &ComponentStorage<C>,
+        field: &str,
+        key: &str,
+    ) -> Vec<Entity> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.refresh(storage);
+        inner
+            .entries
+            .get(field)
+            .and_then(|entries| entries.get(key))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl SecondaryIndicesInner {
+    fn refresh<C: Component>(&mut self, storage: &ComponentStorage<C>) {
+        if self.all_dirty {
+            self.all_dirty = false;
+            self.dirty.clear();
+            self.entries.clear();
+            self.keys.clear();
+            let (entities, storage) = storage.open();
+            for &entity in entities {
+                self.add(entity, storage.fetch(entity).unwrap().index_keys());
+            }
+            return;
+        }
+
+        for entity in std::mem::take(&mut self.dirty) {
+            if let Some(keys) = self.keys.remove(&entity) {
+                for (field, key) in keys {
+                    if let Some(entities) = self
+                        .entries
+                        .get_mut(field)
+                        .and_then(|entries| entries.get_mut(&key))
+                    {
+                        entities.retain(|&e| e != entity);
+                        if entities.is_empty() {
+                            self.entries.get_mut(field).unwrap().remove(&key);
+                        }
+                    }
+                }
+            }
+            if let Some(component) = storage.fetch(entity) {
+                self.add(entity, component.index_keys());
+            }
+        }
+    }
+
+    fn add(&mut self, entity: Entity, keys: Vec<(&'static str, IndexKey)>) {
+        if keys.is_empty() {
+            return;
+        }
+        self.indexed = true;
+        for (field, key) in &keys {
+            self.entries
+                .entry(*field)
+                .or_default()
+                .entry(key.clone())
+                .or_default()
+                .push(entity);
+        }
+        self.keys.insert(entity, keys);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[component(index = "name", index = "team")]
+    struct Player {
+        name: String,
+        team: u32,
+    }
+
+    #[component]
+    struct Target {
+        #[entity_ref(nullify)]
+        entity: Option<Entity>,
+    }
+
+    #[test]
+    fn tracked_types() {
+        assert!(Player::has_index());
+        assert!(!Player::has_kill_policy());
+        assert!(!Target::has_index());
+        assert!(Target::has_kill_policy());
+    }
+
+    #[test]
+    fn find_by() {
+        let mut world = World::default();
+        let a = world
+            .create_entity()
+            .with(Player {
+                name: "a".to_owned(),
+                team: 1,
+            })
+            .create();
+        let b = world
+            .create_entity()
+            .with(Player {
+                name: "b".to_owned(),
+                team: 1,
+            })
+            .create();
+
+        let mut players = unsafe { WriteComps::<Player>::fetch(&world) };
+        assert_eq!(players.find_by("name", "a"), vec![a]);
+        assert_eq!(players.find_by("team", &1), vec![a, b]);
+        assert!(players.find_by("team", &2).is_empty());
+
+        for player in (&mut players).join() {
+            if player.name == "b" {
+                player.team = 2;
+            }
+        }
+        assert_eq!(players.find_by("team", &1), vec![a]);
+        assert_eq!(players.find_by("team", &2), vec![b]);
+
+        players.remove(a);
+        assert!(players.find_by("name", "a").is_empty());
+        players.insert(
+            a,
+            Player {
+                name: "c".to_owned(),
+                team: 2,
+            },
+        );
+
+        let players = unsafe { RBWComps::<Player>::fetch(&world) };
+        assert_eq!(players.find_by("name", "c"), vec![a]);
+        assert_eq!(players.find_by("team", &2).len(), 2);
+    }
+}
@@ -6,13 +6,19 @@ use serde::{Deserialize, Serialize};
 
 use tb_core::*;
 
-use crate::{join, Component, Entities, Entity, EntityRef};
+use super::referrers::Referrers;
+use super::secondary_index::SecondaryIndices;
+use crate::{index_key, join, CleanDanglingEntityRef, Component, Entities, Entity, EntityRef};
 
 #[derive(Serialize, Deserialize)]
 pub struct ComponentStorage<C: Component> {
     components: Vec<C>,
     entities: Vec<Entity>,
     entity_to_index: EntityToIndex,
+    #[serde(skip)]
+    indices: SecondaryIndices,
+    #[serde(skip)]
+    referrers: Referrers,
 }
 
 impl<T: Component> ComponentStorage<T> {
@@ -20,12 +26,23 @@ impl<T: Component> ComponentStorage<T> {
         (self.entities.iter(), self)
     }
 
-    pub(crate) fn entity_iter(&self) -> std::iter::Copied<std::slice::Iter<Entity>> {
-        self.entities.iter().copied()
+    pub(crate) fn iter_mut(
+        &mut self,
+    ) -> std::iter::Zip<std::iter::Copied<Iter<'_, Entity>>, std::slice::IterMut<'_, T>> {
+        self.on_all_changed();
+        self.entities
+            .iter()
+            .copied()
+            .zip(self.components.iter_mut())
     }
 
-    pub(crate) fn par_entity_iter(&self) -> rayon::iter::Copied<rayon::slice::Iter<Entity>> {
-        self.entities.par_iter().copied()
+    /// Remove all components
+    pub(crate) fn drain(
+        &mut self,
+    ) -> std::iter::Zip<std::vec::Drain<'_, Entity>, std::vec::Drain<'_, T>> {
+        self.entity_to_index = Default::default();
+        self.on_all_changed();
+        self.entities.drain(..).zip(self.components.drain(..))
     }
 
     pub fn contains(&self, entity: Entity) -> bool {
@@ -38,7 +55,14 @@ impl<T: Component> ComponentStorage<T> {
         self.components.is_empty()
     }
 
+    pub fn reserve(&mut self, additional: usize) {
+        self.components.reserve(additional);
+        self.entities.reserve(additional);
+        self.entity_to_index.reserve(additional);
+    }
+
     pub fn insert(&mut self, entity: Entity, elem: T) {
+        self.on_changed(entity);
         match self.entity_to_index.entry(entity) {
             Entry::Occupied(occupied) => self.components[*occupied.get()] = elem,
             Entry::Vacant(vacant) => {
@@ -51,6 +75,7 @@ impl<T: Component> ComponentStorage<T> {
 
     pub(crate) fn remove(&mut self, entity: Entity) {
         if let Some(removed_index) = self.entity_to_index.remove(&entity) {
+            self.on_changed(entity);
             let last_entity = *self.entities.last().unwrap();
             self.entities.swap_remove(removed_index);
             self.components.swap_remove(removed_index);
@@ -69,17 +94,76 @@ impl<T: Component> ComponentStorage<T> {
     pub fn fetch_mut(&mut self, entity: Entity) -> Option<&mut T> {
         match self.entity_to_index.get(&entity) {
             None => None,
-            Some(&index) => Some(&mut self.components[index]),
+            Some(&index) => {
+                self.on_changed(entity);
+                Some(&mut self.components[index])
+            }
+        }
+    }
+
+    /// Only the types declaring indices or kill policies track the changed components,
+    /// which is decided by `#[component]` at compile time
+    fn on_changed(&mut self, entity: Entity) {
+        if T::has_index() {
+            self.indices.on_changed(entity);
+        }
+        if T::has_kill_policy() {
+            self.referrers.on_changed(entity);
+        }
+    }
+
+    fn on_all_changed(&mut self) {
+        if T::has_index() {
+            self.indices.on_all_changed();
+        }
+        if T::has_kill_policy() {
+            self.referrers.on_all_changed();
+        }
+    }
+
+    /// Entities whose `field` equals `value`.
+    /// Only the fields declared by `#[component(index = "...")]` are indexed.
+    pub fn find_by<V: Serialize + ?Sized>(&self, field: &str, value: &V) -> Vec<Entity> {
+        self.indices.find(self, field, &index_key(value))
+    }
+}
+
+impl<T: CleanDanglingEntityRef> ComponentStorage<T> {
+    /// The entities whose component references `target` in a field with a kill policy
+    pub(crate) fn referrers(&mut self, target: Entity) -> Vec<Entity> {
+        let referrers = &mut self.referrers;
+        if !referrers.is_built() {
+            referrers.set_built();
+            for (&owner, component) in self.entities.iter().zip(self.components.iter_mut()) {
+                referrers.set_refs(owner, policy_refs(component));
+            }
+        } else {
+            for owner in referrers.take_dirty() {
+                let refs = match self.entity_to_index.get(&owner) {
+                    Some(&index) => policy_refs(&mut self.components[index]),
+                    None => vec![],
+                };
+                referrers.set_refs(owner, refs);
+            }
         }
+        referrers.owners_of(target)
     }
 }
 
+fn policy_refs<T: CleanDanglingEntityRef>(component: &mut T) -> Vec<Entity> {
+    let mut refs = vec![];
+    component.for_each_policy_ref(&mut |entity: &mut Entity| refs.push(*entity));
+    refs
+}
+
 impl<T: Component> Default for ComponentStorage<T> {
     fn default() -> Self {
         Self {
             components: Default::default(),
             entities: Default::default(),
             entity_to_index: Default::default(),
+            indices: Default::default(),
+            referrers: Default::default(),
         }
     }
 }
@@ -115,6 +199,14 @@ impl LocalToWorldLink {
             Some(entity) => *entity,
         }
     }
+
+    pub fn get(&self, local: Entity) -> Option<Entity> {
+        self.0.get(&local).copied()
+    }
+
+    pub(crate) fn as_map(&self) -> &HashMap<Entity, Entity> {
+        &self.0
+    }
 }
 
 pub trait ConvertToWorld {
@@ -150,4 +242,7 @@ impl EntityToIndex {
     pub(crate) fn entry(&mut self, entity: Entity) -> Entry<'_, Entity, usize> {
         self.entity_to_index.entry(entity)
     }
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.entity_to_index.reserve(additional)
+    }
 }
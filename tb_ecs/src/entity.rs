@@ -1,9 +1,10 @@
 use std::any::TypeId;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::ops::{Deref, DerefMut, Index, IndexMut};
-use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use bit_set::BitSet;
 use rayon::iter::plumbing::UnindexedConsumer;
@@ -14,7 +15,7 @@ use serde::{Deserialize, Serialize};
 use tb_core::*;
 
 use crate::registry::{ComponentIndex, ComponentRegistry};
-use crate::{Component, Join, SystemData, World, WriteComps};
+use crate::{Component, Disabled, Join, LocalToWorldLink, World};
 
 #[derive(Deserialize, Serialize, Copy, Clone, Hash, PartialEq, Eq, Debug)]
 pub struct Entity {
@@ -44,14 +45,44 @@ impl DerefMut for ComponentMask {
     }
 }
 
-#[derive(Default)]
+/// Entity ids are reserved atomically, component insertions and removals are queued,
+/// both are applied to the archetypes at the next sync point, see `World::maintain`.
+/// The queue is sharded per thread, the changes are applied in the order they were queued.
 pub struct Entities {
+    next_id: AtomicU64,
+    next_change: AtomicU64,
+    pending: Box<[Mutex<Vec<(u64, StructuralChange)>>]>,
     inner: RwLock<EntitiesInner>,
 }
 
+impl Default for Entities {
+    fn default() -> Self {
+        Self {
+            next_id: Default::default(),
+            next_change: Default::default(),
+            pending: (0..rayon::current_num_threads() + 1)
+                .map(|_| Default::default())
+                .collect(),
+            inner: Default::default(),
+        }
+    }
+}
+
+enum StructuralChange {
+    Insert(Entity, ComponentIndex),
+    Remove(Entity, ComponentIndex),
+}
+
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed);
+}
+
 impl Entities {
     pub fn len(&self) -> usize {
-        self.read().len()
+        let inner = self.read();
+        inner.len() + (self.next_id.load(Ordering::Relaxed) - inner.placed_id) as usize
     }
     pub fn is_empty(&self) -> bool {
         self.len() == 0
@@ -62,11 +93,47 @@ impl Entities {
     pub fn par_iter(&self) -> ParEntitiesIter<'_> {
         ParEntitiesIter::new(self.read())
     }
+    /// Reserved entities are alive, they are placed in the archetypes at the next sync point
     pub fn is_alive(&self, entity: Entity) -> bool {
-        self.read().is_alive(entity)
+        let inner = self.read();
+        inner.is_alive(entity)
+            || (entity.id >= inner.placed_id && entity.id < self.next_id.load(Ordering::Relaxed))
     }
+    /// Create an entity without locking, it is alive at once and joined after the next sync point
     pub fn new_entity(&self) -> Entity {
-        self.write().new_entity()
+        self.reserve_entity()
+    }
+    /// Reserve an entity without locking, same as `new_entity`
+    pub fn reserve_entity(&self) -> Entity {
+        Entity::new(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+    pub(crate) fn new_entities(&self, components: &[ComponentIndex], count: usize) -> Vec<Entity> {
+        let mut inner = self.write();
+        let first_id = self.next_id.fetch_add(count as u64, Ordering::Relaxed);
+        inner.place_reserved(first_id);
+        inner.new_entities(components, first_id, count)
+    }
+    /// Apply the reserved entities and the queued component changes to the archetypes
+    pub fn maintain(&self) {
+        let next_id = self.next_id.load(Ordering::Relaxed);
+        let mut changes: Vec<_> = self
+            .pending
+            .iter()
+            .flat_map(|shard| std::mem::take(&mut *shard.lock().unwrap()))
+            .collect();
+        changes.sort_unstable_by_key(|(order, _)| *order);
+        let mut inner = self.write();
+        inner.place_reserved(next_id);
+        for (_, change) in changes {
+            match change {
+                StructuralChange::Insert(entity, component_index) => {
+                    inner.on_component_inserted(entity, component_index)
+                }
+                StructuralChange::Remove(entity, component_index) => {
+                    inner.on_component_removed(entity, component_index)
+                }
+            }
+        }
     }
     fn kill(&self, entity: Entity, for_each_component: impl FnMut(usize)) {
         self.write().kill(entity, for_each_component)
@@ -78,30 +145,224 @@ impl Entities {
         self.inner.write().unwrap()
     }
     pub(crate) fn on_component_inserted<C: Component>(&self, entity: Entity) {
-        let component_index = ComponentIndex::get::<C>();
-        self.inner
-            .write()
-            .unwrap()
-            .on_component_inserted(entity, component_index);
+        self.on_component_index_inserted(entity, ComponentIndex::get::<C>());
+    }
+    pub(crate) fn on_component_index_inserted(
+        &self,
+        entity: Entity,
+        component_index: ComponentIndex,
+    ) {
+        self.push_change(StructuralChange::Insert(entity, component_index));
+    }
+    fn push_change(&self, change: StructuralChange) {
+        let shard = SHARD.with(|shard| *shard) % self.pending.len();
+        let mut pending = self.pending[shard].lock().unwrap();
+        // Ordered under the lock of the shard, so every shard is already sorted
+        let order = self.next_change.fetch_add(1, Ordering::Relaxed);
+        pending.push((order, change));
+    }
+    /// Move the entity to the archetype of `components` directly, placing the reserved entities
+    pub(crate) fn set_component_indices(&self, entity: Entity, components: &[ComponentIndex]) {
+        let mut inner = self.write();
+        inner.place_reserved(self.next_id.load(Ordering::Relaxed));
+        inner.set_components(entity, components);
+    }
+    pub(crate) fn component_indices(&self, entity: Entity) -> Vec<ComponentIndex> {
+        let inner = self.read();
+        match inner.entity_to_index.get(&entity) {
+            None => vec![],
+            Some(entity_index) => inner.archetypes_component_mask[entity_index.archetype]
+                .iter()
+                .map(ComponentIndex::from)
+                .collect(),
+        }
+    }
+    pub(crate) fn on_component_removed<C: Component>(&self, entity: Entity) {
+        self.on_component_index_removed(entity, ComponentIndex::get::<C>());
+    }
+    pub(crate) fn on_component_index_removed(
+        &self,
+        entity: Entity,
+        component_index: ComponentIndex,
+    ) {
+        self.push_change(StructuralChange::Remove(entity, component_index));
+    }
+    /// Collect the entities of all archetypes matched by `matcher`, without caching the match.
+    pub(crate) fn matched_entities(&self, matcher: &ArchetypeMatcher) -> Vec<Entity> {
+        let inner = self.read();
+        inner
+            .archetypes_component_mask
+            .iter()
+            .zip(&inner.archetypes_entities)
+            .filter(|(mask, _)| matcher.is_matched(mask))
+            .flat_map(|(_, entities)| entities.iter().copied())
+            .collect()
     }
 }
 
 impl World {
+    /// Kill the entity, then apply the kill policies of the components referencing it.
     pub fn kill(&mut self, entity: Entity) {
+        self.maintain();
+        let mut pending = vec![entity];
+        while let Some(entity) = pending.pop() {
+            let entities = unsafe { self.fetch::<Entities>() };
+            if !entities.is_alive(entity) {
+                continue;
+            }
+            unsafe {
+                entities.kill(entity, |component_index| {
+                    ComponentRegistry::operation(component_index.into())
+                        .0
+                        .remove_from_world(self, entity)
+                });
+                ComponentRegistry::clean_dangling_entity_refs(self, entity, &mut pending);
+            }
+        }
+        self.maintain();
+    }
+
+    /// Make exactly the entities of `alive` alive, e.g. to follow the entities of another world.
+    /// The others are killed, as the ids before the largest one which were never used here.
+    pub fn mirror_entities(&mut self, alive: &[Entity]) {
+        let entities = self.insert(Entities::default);
+        if let Some(max_id) = alive.iter().map(|entity| entity.id).max() {
+            entities.next_id.fetch_max(max_id + 1, Ordering::Relaxed);
+        }
+        entities.maintain();
+        let alive: HashSet<Entity> = alive.iter().copied().collect();
+        let dead: Vec<Entity> = entities
+            .iter()
+            .filter(|entity| !alive.contains(entity))
+            .collect();
+        for entity in dead {
+            self.kill(entity);
+        }
+    }
+
+    /// A sync point, apply the deferred structural changes of `Entities`
+    pub fn maintain(&mut self) {
+        if let Ok(entities) = unsafe { self.try_fetch::<Entities>() } {
+            entities.maintain();
+        }
+    }
+
+    /// Remove the components of the types from all entities and drop their storages,
+    /// e.g. before the plugin registering the types is unloaded.
+    pub fn remove_component_types(&mut self, component_indices: &[ComponentIndex]) {
+        self.maintain();
+        if let Ok(entities) = unsafe { self.try_fetch::<Entities>() } {
+            for &component_index in component_indices {
+                let mut matcher = ArchetypeMatcher::default();
+                matcher.add_all(component_index);
+                matcher.include_disabled();
+                for entity in entities.matched_entities(&matcher) {
+                    entities.on_component_index_removed(entity, component_index);
+                }
+            }
+        }
+        self.maintain();
+        for &component_index in component_indices {
+            ComponentRegistry::operation(component_index)
+                .0
+                .remove_storage(self);
+        }
+    }
+}
+
+impl World {
+    /// Create a copy of the entity with clones of all its components
+    pub fn clone_entity(&mut self, entity: Entity) -> Entity {
+        self.clone_entities(&[entity])[0]
+    }
+
+    /// Create copies of the entities with clones of all their components.
+    /// References between the entities are remapped to the copies,
+    /// references to other entities are left unchanged.
+    /// To clone an entity with its descendants, see `WorldHierarchy::clone_subtree` in `tb_engine`.
+    pub fn clone_entities(&mut self, sources: &[Entity]) -> Vec<Entity> {
+        self.insert(Entities::default);
+        self.maintain();
         let entities = unsafe { self.fetch::<Entities>() };
-        unsafe {
-            entities.kill(entity, |component_index| {
-                ComponentRegistry::operation(component_index.into())
+        let mut map = HashMap::with_capacity(sources.len());
+        let mut clones = Vec::with_capacity(sources.len());
+        for &source in sources {
+            let component_indices = entities.component_indices(source);
+            let clone = entities.new_entities(&component_indices, 1)[0];
+            for &component_index in &component_indices {
+                unsafe {
+                    ComponentRegistry::operation(component_index)
+                        .0
+                        .clone_component(self, source, clone);
+                }
+            }
+            map.insert(source, clone);
+            clones.push((clone, component_indices));
+        }
+
+        for (clone, component_indices) in &clones {
+            for &component_index in component_indices {
+                unsafe {
+                    ComponentRegistry::remap_entity_refs(self, *clone, component_index, &map);
+                }
+            }
+        }
+        clones.into_iter().map(|(clone, _)| clone).collect()
+    }
+
+    /// Move all entities and their components of `other` into the world.
+    /// Every entity of `other` gets a fresh entity through the returned link.
+    /// References to the entities of `other` are remapped with it,
+    /// references to other entities, dead or of this world, are left unchanged.
+    pub fn merge_from(&mut self, mut other: World) -> LocalToWorldLink {
+        self.insert(Entities::default);
+        self.maintain();
+        other.maintain();
+        let mut link = LocalToWorldLink::default();
+        let mut merged = vec![];
+        if let Ok(other_entities) = unsafe { other.try_fetch::<Entities>() } {
+            let entities = unsafe { self.fetch::<Entities>() };
+            let locals: Vec<Entity> = other_entities.iter().collect();
+            merged.reserve(locals.len());
+            for local in locals {
+                let component_indices = other_entities.component_indices(local);
+                let entity = link.build_link(local, entities);
+                entities.set_component_indices(entity, &component_indices);
+                merged.push((entity, component_indices));
+            }
+        }
+
+        let component_indices: HashSet<ComponentIndex> = merged
+            .iter()
+            .flat_map(|(_, component_indices)| component_indices.iter().copied())
+            .collect();
+        for component_index in component_indices {
+            unsafe {
+                ComponentRegistry::operation(component_index)
                     .0
-                    .remove_from_world(self, entity)
-            });
+                    .move_components(&other, self, &link);
+            }
+        }
+
+        for (entity, component_indices) in &merged {
+            for &component_index in component_indices {
+                unsafe {
+                    ComponentRegistry::remap_entity_refs(
+                        self,
+                        *entity,
+                        component_index,
+                        link.as_map(),
+                    );
+                }
+            }
         }
+        link
     }
 }
 
 #[derive(Default)]
 pub struct EntitiesInner {
-    next_id: u64,
+    placed_id: u64,
     len: usize,
     matched_entities_map: RwLock<HashMap<TypeId, RwLock<MatchedEntities>>>,
     entity_to_index: HashMap<Entity, EntityIndex>,
@@ -131,6 +392,7 @@ impl EntitiesInner {
                 return;
             }
         };
+        self.len -= 1;
 
         let entities = &mut self.archetypes_entities[entity_index.archetype];
         let last = *entities.last().unwrap();
@@ -183,6 +445,48 @@ impl EntitiesInner {
         self.transfer(entity, entity_index, next_archetype);
     }
 
+    fn set_components(&mut self, entity: Entity, components: &[ComponentIndex]) {
+        let entity_index = match self.entity_to_index.get(&entity).copied() {
+            Some(index) => index,
+            None => {
+                return;
+            }
+        };
+        let mut mask = ComponentMask::default();
+        for component_index in components {
+            mask.insert(**component_index);
+        }
+        let archetype = self.find_or_insert_archetype(mask);
+        if archetype != entity_index.archetype {
+            self.transfer(entity, entity_index, archetype);
+        }
+    }
+
+    fn on_component_removed(&mut self, entity: Entity, component_index: ComponentIndex) {
+        let entity_index = match self.entity_to_index.get(&entity).copied() {
+            Some(index) => index,
+            None => {
+                return;
+            }
+        };
+        if !self.archetypes_component_mask[entity_index.archetype].contains(*component_index) {
+            return;
+        }
+        let next_archetype = self.archetypes_remove_to_next[entity_index.archetype]
+            .get(&component_index)
+            .copied();
+        let next_archetype = next_archetype.unwrap_or_else(|| {
+            let mut next_mask = self.archetypes_component_mask[entity_index.archetype].clone();
+            next_mask.remove(*component_index);
+            let next_archetype = self.find_or_insert_archetype(next_mask);
+            self.archetypes_remove_to_next[entity_index.archetype]
+                .insert(component_index, next_archetype);
+            next_archetype
+        });
+
+        self.transfer(entity, entity_index, next_archetype);
+    }
+
     fn transfer(&mut self, entity: Entity, from: EntityIndex, to: ArchetypeIndex) {
         let from_entities = &mut self.archetypes_entities[from.archetype];
         let from_last = *from_entities.last().unwrap();
@@ -198,11 +502,40 @@ impl EntitiesInner {
         self.len
     }
 
-    pub fn new_entity(&mut self) -> Entity {
-        let id = self.next_id;
-        self.next_id += 1;
-        let entity = Entity { id };
+    /// Place the reserved entities before `next_id` in the empty archetype
+    fn place_reserved(&mut self, next_id: u64) {
+        if self.placed_id >= next_id {
+            return;
+        }
         let archetype = self.find_or_insert_archetype(ComponentMask::default());
+        for id in self.placed_id..next_id {
+            self.new_entity_in(archetype, Entity { id });
+        }
+        self.placed_id = next_id;
+    }
+
+    /// Create the entities of the ids reserved from `first_id` directly in the archetype of `components`
+    fn new_entities(
+        &mut self,
+        components: &[ComponentIndex],
+        first_id: u64,
+        count: usize,
+    ) -> Vec<Entity> {
+        let mut mask = ComponentMask::default();
+        for component_index in components {
+            mask.insert(**component_index);
+        }
+        let archetype = self.find_or_insert_archetype(mask);
+        self.archetypes_entities[archetype].reserve(count);
+        self.entity_to_index.reserve(count);
+        let entities = (first_id..first_id + count as u64)
+            .map(|id| self.new_entity_in(archetype, Entity { id }))
+            .collect();
+        self.placed_id = first_id + count as u64;
+        entities
+    }
+
+    fn new_entity_in(&mut self, archetype: ArchetypeIndex, entity: Entity) -> Entity {
         let new_entity_index = self.push_entity(archetype, entity);
         self.entity_to_index.insert(entity, new_entity_index);
         self.len += 1;
@@ -327,14 +660,17 @@ impl IndexMut<ArchetypeIndex> for Vec<HashMap<ComponentIndex, ArchetypeIndex>> {
 pub struct EntityCreator<'r> {
     created: bool,
     entity: Entity,
+    component_indices: Vec<ComponentIndex>,
     world: &'r mut World,
 }
 
 impl EntityCreator<'_> {
     pub fn with<C: Component>(&mut self, c: C) -> &mut Self {
-        self.world.insert_components::<C>();
-        let mut components = unsafe { WriteComps::<C>::fetch(self.world) };
-        components.insert(self.entity, c);
+        self.world.insert_components::<C>().insert(self.entity, c);
+        let component_index = ComponentIndex::get::<C>();
+        if !self.component_indices.contains(&component_index) {
+            self.component_indices.push(component_index);
+        }
         self
     }
     pub fn create(&mut self) -> Entity {
@@ -344,7 +680,10 @@ impl EntityCreator<'_> {
 }
 
 impl Drop for EntityCreator<'_> {
+    /// Move the entity to its archetype at once, instead of once per component
     fn drop(&mut self) {
+        unsafe { self.world.fetch::<Entities>() }
+            .set_component_indices(self.entity, &self.component_indices);
         if !self.created {
             self.world.kill(self.entity)
         }
@@ -357,6 +696,7 @@ impl World {
         EntityCreator {
             created: false,
             entity,
+            component_indices: vec![],
             world: self,
         }
     }
@@ -420,6 +760,7 @@ pub struct ArchetypeMatcher {
     all: ComponentMask,
     none: ComponentMask,
     // todo: any: ComponentMask,
+    include_disabled: bool,
 }
 
 impl ArchetypeMatcher {
@@ -429,9 +770,15 @@ impl ArchetypeMatcher {
     pub(crate) fn add_none(&mut self, component_index: ComponentIndex) {
         self.none.insert(*component_index);
     }
+    /// Match the archetypes with `Disabled`, which are skipped by default
+    pub(crate) fn include_disabled(&mut self) {
+        self.include_disabled = true;
+    }
     pub(crate) fn is_matched(&self, archetype_component_mask: &ComponentMask) -> bool {
         self.all.is_subset(archetype_component_mask)
             && self.none.is_disjoint(archetype_component_mask)
+            && (self.include_disabled
+                || !archetype_component_mask.contains(*ComponentIndex::get::<Disabled>()))
     }
 }
 
@@ -610,7 +957,25 @@ impl<'e> ParallelIterator for ParMatchedEntitiesIter<'e> {
 #[cfg(test)]
 mod tests {
     use crate::entity::Entities;
-    use crate::{Entity, World};
+    use crate::*;
+
+    #[component]
+    struct Follow {
+        #[entity_ref(nullify)]
+        target: Option<Entity>,
+    }
+
+    #[component]
+    struct Attached {
+        #[entity_ref(cascade)]
+        to: Entity,
+    }
+
+    #[component]
+    struct Link {
+        #[entity_ref(remove)]
+        other: Entity,
+    }
 
     #[test]
     fn entity_life() {
@@ -630,6 +995,66 @@ mod tests {
         assert!(entities.is_alive(entity0));
     }
 
+    #[test]
+    fn deferred_structural_changes() {
+        let mut world = World::default();
+        world.insert(Entities::default);
+        world.insert_components::<Follow>();
+        let reserved = unsafe { world.fetch::<Entities>() }.reserve_entity();
+        let created = unsafe { world.fetch::<Entities>() }.new_entity();
+        assert_eq!(reserved.id, 0);
+        assert_eq!(created.id, 1);
+
+        let mut follows = unsafe { WriteComps::<Follow>::fetch(&world) };
+        follows.insert(reserved, Follow { target: None });
+        follows.insert(created, Follow { target: None });
+        assert_eq!((&mut follows).join().count(), 0);
+
+        world.maintain();
+        let entities = unsafe { world.fetch::<Entities>() };
+        assert!(entities.is_alive(reserved));
+        assert_eq!(entities.len(), 2);
+        let mut follows = unsafe { WriteComps::<Follow>::fetch(&world) };
+        assert_eq!((&mut follows).join().count(), 2);
+    }
+
+    #[test]
+    fn structural_changes_across_threads() {
+        let mut world = World::default();
+        world.insert_components::<Follow>();
+        let entity = world.create_entity().create();
+        world.maintain();
+        let entities = unsafe { world.fetch::<Entities>() };
+        let follow = ComponentIndex::get::<Follow>();
+        rayon::scope(|s| s.spawn(|_| entities.on_component_index_inserted(entity, follow)));
+        entities.on_component_index_removed(entity, follow);
+        entities.maintain();
+        assert!(entities.component_indices(entity).is_empty());
+    }
+
+    #[test]
+    fn mirror_entities() {
+        let mut world = World::default();
+        let kept = world
+            .create_entity()
+            .with(Link {
+                other: Entity::new(5),
+            })
+            .create();
+        let killed = world.create_entity().create();
+        world.mirror_entities(&[kept, Entity::new(3)]);
+
+        let entities = unsafe { world.fetch::<Entities>() };
+        assert!(entities.is_alive(kept));
+        assert!(!entities.is_alive(killed));
+        assert!(!entities.is_alive(Entity::new(2)));
+        assert!(entities.is_alive(Entity::new(3)));
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities.new_entity().id, 4);
+        let links = unsafe { world.fetch_components::<Link>() };
+        assert!(links.contains(kept));
+    }
+
     #[test]
     fn create_entity_failed() {
         let mut world = World::default();
@@ -645,4 +1070,166 @@ mod tests {
             .entity_to_index
             .contains_key(&Entity { id: 1 }));
     }
+
+    #[test]
+    fn kill_policies() {
+        let mut world = World::default();
+        let target = world.create_entity().create();
+        let follower = world
+            .create_entity()
+            .with(Follow {
+                target: Some(target),
+            })
+            .create();
+        let attached = world.create_entity().with(Attached { to: target }).create();
+        let attached_child = world
+            .create_entity()
+            .with(Attached { to: attached })
+            .create();
+        let linked = world.create_entity().with(Link { other: target }).create();
+
+        world.kill(target);
+
+        let entities = unsafe { world.fetch::<Entities>() };
+        assert!(entities.is_alive(follower));
+        assert!(!entities.is_alive(attached));
+        assert!(!entities.is_alive(attached_child));
+        assert!(entities.is_alive(linked));
+        let follows = unsafe { world.fetch_components::<Follow>() };
+        assert_eq!(follows.fetch(follower).unwrap().target, None);
+        let links = unsafe { world.fetch_components::<Link>() };
+        assert!(!links.contains(linked));
+    }
+
+    #[test]
+    fn kill_policies_follow_changed_refs() {
+        let mut world = World::default();
+        let first = world.create_entity().create();
+        let second = world.create_entity().create();
+        let follower = world
+            .create_entity()
+            .with(Follow {
+                target: Some(first),
+            })
+            .create();
+        let unrelated = world.create_entity().create();
+        world.kill(unrelated);
+
+        unsafe { world.fetch_components_mut::<Follow>() }
+            .fetch_mut(follower)
+            .unwrap()
+            .target = Some(second);
+        world.kill(first);
+        let follows = unsafe { world.fetch_components::<Follow>() };
+        assert_eq!(follows.fetch(follower).unwrap().target, Some(second));
+        world.kill(second);
+        let follows = unsafe { world.fetch_components::<Follow>() };
+        assert_eq!(follows.fetch(follower).unwrap().target, None);
+    }
+
+    #[test]
+    fn clone_entities() {
+        let mut world = World::default();
+        let external = world.create_entity().create();
+        let leader = world
+            .create_entity()
+            .with(Follow {
+                target: Some(external),
+            })
+            .create();
+        let follower = world
+            .create_entity()
+            .with(Follow {
+                target: Some(leader),
+            })
+            .with(Link { other: leader })
+            .create();
+
+        let clones = world.clone_entities(&[leader, follower]);
+
+        let follows = unsafe { world.fetch_components::<Follow>() };
+        let links = unsafe { world.fetch_components::<Link>() };
+        assert_eq!(follows.fetch(clones[0]).unwrap().target, Some(external));
+        assert_eq!(follows.fetch(clones[1]).unwrap().target, Some(clones[0]));
+        assert_eq!(links.fetch(clones[1]).unwrap().other, clones[0]);
+        assert_eq!(follows.fetch(follower).unwrap().target, Some(leader));
+
+        let clone = world.clone_entity(follower);
+        let follows = unsafe { world.fetch_components::<Follow>() };
+        assert_eq!(follows.fetch(clone).unwrap().target, Some(leader));
+    }
+
+    #[test]
+    fn merge_from() {
+        let mut world = World::default();
+        let existing = world
+            .create_entity()
+            .with(Link {
+                other: Entity::new(0),
+            })
+            .create();
+
+        let mut scratch = World::default();
+        let leader = scratch.create_entity().create();
+        let follower = scratch
+            .create_entity()
+            .with(Follow {
+                target: Some(leader),
+            })
+            .with(Link { other: leader })
+            .create();
+
+        let link = world.merge_from(scratch);
+
+        let merged_leader = link.get(leader).unwrap();
+        let merged_follower = link.get(follower).unwrap();
+        assert_ne!(merged_leader, existing);
+        assert_ne!(merged_follower, existing);
+        let entities = unsafe { world.fetch::<Entities>() };
+        assert!(entities.is_alive(merged_leader));
+        assert_eq!(entities.len(), 3);
+        let follows = unsafe { world.fetch_components::<Follow>() };
+        let links = unsafe { world.fetch_components::<Link>() };
+        assert_eq!(
+            follows.fetch(merged_follower).unwrap().target,
+            Some(merged_leader)
+        );
+        assert_eq!(links.fetch(merged_follower).unwrap().other, merged_leader);
+        assert_eq!(links.fetch(existing).unwrap().other, Entity::new(0));
+
+        let (follows, links) = unsafe { <(RBWComps<Follow>, RBWComps<Link>)>::fetch(&world) };
+        let joined: Vec<_> = (&follows, &links)
+            .join()
+            .map(|(follow, _)| follow.target)
+            .collect();
+        assert_eq!(joined, vec![Some(merged_leader)]);
+    }
+
+    #[test]
+    fn merge_from_keeps_unlinked_refs() {
+        let mut world = World::default();
+        let existing = world.create_entity().create();
+
+        let mut scratch = World::default();
+        let dangling = Entity::new(100);
+        let local = scratch
+            .create_entity()
+            .with(Follow {
+                target: Some(existing),
+            })
+            .with(Link { other: dangling })
+            .create();
+
+        let link = world.merge_from(scratch);
+
+        let merged = link.get(local).unwrap();
+        assert_eq!(link.get(dangling), None);
+        let entities = unsafe { world.fetch::<Entities>() };
+        assert_eq!(entities.len(), 2);
+        assert!(!entities.is_alive(dangling));
+        let follows = unsafe { world.fetch_components::<Follow>() };
+        let links = unsafe { world.fetch_components::<Link>() };
+        assert_eq!(follows.fetch(merged).unwrap().target, Some(existing));
+        assert_eq!(links.fetch(merged).unwrap().other, dangling);
+    }
 }
@@ -3,7 +3,10 @@ use rayon::iter::ParallelIterator;
 
 use tb_core::*;
 
-use crate::{ArchetypeMatcher, Entities, Entity, MatchedEntitiesIter, ParMatchedEntitiesIter};
+use crate::{
+    ArchetypeMatcher, Entities, Entity, IncludeDisabled, MatchedEntitiesIter,
+    ParMatchedEntitiesIter,
+};
 
 pub trait Join<'j>: Sized {
     type Element: 'static;
@@ -25,6 +28,10 @@ pub trait Join<'j>: Sized {
             elem_fetcher,
         }
     }
+    /// Also visit the disabled entities
+    fn include_disabled(self) -> IncludeDisabled<Self> {
+        IncludeDisabled::new(self)
+    }
     fn open(self) -> (Self::EntitiesIter, Self::ElementFetcher);
     fn par_open(self) -> (Self::ParEntitiesIter, Self::ElementFetcher);
     fn entities(&self) -> &'j Entities;
@@ -1,5 +1,5 @@
 use std::cell::UnsafeCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -9,11 +9,14 @@ use tb_core::algorithm::topological_sort::{Node, TopologicalGraph};
 use tb_core::event_channel::ReaderHandle;
 use tb_core::*;
 
-use crate::{System, SystemData, SystemInfo, SystemRegistry, World};
+use crate::{ExclusiveSystem, System, SystemData, SystemInfo, SystemRegistry, World};
 
 pub struct Scheduler {
     resources_change_event_reader: ReaderHandle,
     systems: Vec<RunnableCell>,
+    system_infos: Vec<&'static SystemInfo>,
+    idle_systems: HashMap<&'static SystemInfo, Box<dyn RunnableSystem>>,
+    stages: Vec<Stage>,
     dependants: Vec<DashSet<usize>>,
     dependencies_counter_cache: Vec<AtomicUsize>,
     dependencies_counter: Vec<AtomicUsize>,
@@ -25,6 +28,9 @@ impl Scheduler {
         let resources_change_event_reader = channel.register();
         let mut scheduler = Self {
             systems: vec![],
+            system_infos: vec![],
+            idle_systems: Default::default(),
+            stages: vec![],
             dependants: vec![],
             dependencies_counter_cache: vec![],
             dependencies_counter: vec![],
@@ -49,11 +55,16 @@ impl Scheduler {
             },
         );
 
-        (0..self.systems.len())
-            .into_par_iter()
-            .for_each(|i| unsafe {
+        for stage in &self.stages {
+            stage.systems.par_iter().for_each(|&i| unsafe {
                 self.run_system_recursive(i, world);
-            })
+            });
+            world.maintain();
+            if let Some(exclusive) = stage.exclusive {
+                self.systems[exclusive].get_mut().run_exclusive(world);
+                world.maintain();
+            }
+        }
     }
 
     unsafe fn run_system_recursive(&self, i: usize, world: &World) {
@@ -67,23 +78,55 @@ impl Scheduler {
         }
     }
 
-    fn refresh_systems(&mut self, world: &mut World) {
-        let mut sr = SystemRegistry::instance();
+    /// Rebuild the stages from the registered systems.
+    /// The instances of unregistered systems are torn down and dropped.
+    pub fn refresh_systems(&mut self, world: &mut World) {
+        let mut instances: HashMap<&'static SystemInfo, Box<dyn RunnableSystem>> = self
+            .system_infos
+            .drain(..)
+            .zip(self.systems.drain(..).map(RunnableCell::into_inner))
+            .chain(self.idle_systems.drain())
+            .collect();
+        // setup and teardown may register or unregister systems, so the registry is not locked
+        // while they run, and the instances are synced again if it has changed meanwhile
+        let mut sr = loop {
+            let registered_infos: HashSet<&'static SystemInfo> = {
+                let mut sr = SystemRegistry::instance();
+                let registered_infos = sr.systems().par_iter().map(|(&info, _node)| info);
+                registered_infos.collect()
+            };
+            Self::sync_instances(&mut instances, &registered_infos, world);
+
+            let mut sr = SystemRegistry::instance();
+            let systems = sr.systems();
+            let synced = instances.keys().all(|info| systems.node(info).is_some())
+                && systems
+                    .par_iter()
+                    .all(|(info, _node)| instances.contains_key(info));
+            if synced {
+                break sr;
+            }
+        };
         let sr: &mut SystemRegistry = &mut sr;
         let systems = sr.systems();
+
         let infos: Vec<_> = systems
             .par_iter()
             .filter(|(&system_info, _node)| system_info.is_resources_existed(world))
             .collect();
 
         let mut info_to_index = HashMap::with_capacity(infos.len());
-        self.systems.clear();
         self.systems.reserve(infos.len());
+        self.system_infos.reserve(infos.len());
         for (i, (&info, _node)) in infos.iter().enumerate() {
-            self.systems
-                .push(RunnableCell(UnsafeCell::new(info.create_system())));
+            let system = instances.remove(info).unwrap();
+            self.systems.push(RunnableCell(UnsafeCell::new(system)));
+            self.system_infos.push(info);
             info_to_index.insert(info, i);
         }
+        self.idle_systems = instances;
+
+        let system_stages = self.build_stages(&infos, &info_to_index, systems);
 
         self.dependants
             .par_iter_mut()
@@ -92,7 +135,10 @@ impl Scheduler {
         infos
             .par_iter()
             .enumerate()
-            .for_each(|(i, (_info, node))| self.add_dependants(i, node, &info_to_index, systems));
+            .filter(|(_i, (info, _node))| !info.is_exclusive())
+            .for_each(|(i, (_info, node))| {
+                self.add_dependants(i, node, &info_to_index, &system_stages, systems)
+            });
 
         self.dependencies_counter_cache
             .par_iter()
@@ -113,26 +159,165 @@ impl Scheduler {
             });
     }
 
+    /// Tear down all system instances, e.g. before the world is dropped.
+    /// The scheduler has no systems to run afterwards.
+    pub fn shutdown(&mut self, world: &mut World) {
+        let systems = self.systems.drain(..).map(RunnableCell::into_inner);
+        for mut system in systems.chain(self.idle_systems.drain().map(|(_info, s)| s)) {
+            system.teardown(world);
+        }
+        self.system_infos.clear();
+        self.stages.clear();
+        self.dependants.clear();
+        self.dependencies_counter_cache.clear();
+        self.dependencies_counter.clear();
+    }
+
+    /// Create and set up the instances of the registered systems,
+    /// tear down and drop the instances of the unregistered ones.
+    fn sync_instances(
+        instances: &mut HashMap<&'static SystemInfo, Box<dyn RunnableSystem>>,
+        registered_infos: &HashSet<&'static SystemInfo>,
+        world: &mut World,
+    ) {
+        for &info in registered_infos {
+            instances.entry(info).or_insert_with(|| {
+                let mut system = info.create_system();
+                system.setup(world);
+                system
+            });
+        }
+        instances.retain(|info, system| {
+            let registered = registered_infos.contains(info);
+            if !registered {
+                system.teardown(world);
+            }
+            registered
+        });
+    }
+
+    /// Split the systems into stages separated by exclusive systems.
+    /// Returns the stage of every system.
+    fn build_stages(
+        &mut self,
+        infos: &[(&&'static SystemInfo, &Node<&'static SystemInfo>)],
+        info_to_index: &HashMap<&SystemInfo, usize>,
+        systems: &TopologicalGraph<&SystemInfo>,
+    ) -> Vec<usize> {
+        let exclusive_dependencies: Vec<HashSet<usize>> = infos
+            .par_iter()
+            .map(|(_info, node)| {
+                let mut visited = HashSet::new();
+                let mut exclusive_dependencies = HashSet::new();
+                Self::collect_exclusive_dependencies(
+                    node,
+                    info_to_index,
+                    systems,
+                    &mut visited,
+                    &mut exclusive_dependencies,
+                );
+                exclusive_dependencies
+            })
+            .collect();
+
+        // An exclusive system always has more exclusive dependencies than the ones it depends on.
+        let mut exclusive_systems: Vec<usize> = (0..infos.len())
+            .filter(|&i| infos[i].0.is_exclusive())
+            .collect();
+        exclusive_systems.sort_by_key(|&i| (exclusive_dependencies[i].len(), infos[i].0.name()));
+
+        let mut exclusive_to_stage = HashMap::with_capacity(exclusive_systems.len());
+        self.stages.clear();
+        for &exclusive in &exclusive_systems {
+            exclusive_to_stage.insert(exclusive, self.stages.len());
+            self.stages.push(Stage {
+                systems: vec![],
+                exclusive: Some(exclusive),
+            });
+        }
+        self.stages.push(Stage::default());
+
+        (0..infos.len())
+            .map(|i| match exclusive_to_stage.get(&i) {
+                Some(&stage) => stage,
+                None => {
+                    let stage = exclusive_dependencies[i]
+                        .iter()
+                        .map(|exclusive| exclusive_to_stage[exclusive] + 1)
+                        .max()
+                        .unwrap_or(0);
+                    self.stages[stage].systems.push(i);
+                    stage
+                }
+            })
+            .collect()
+    }
+
+    fn collect_exclusive_dependencies<'s>(
+        node: &Node<&'s SystemInfo>,
+        info_to_index: &HashMap<&SystemInfo, usize>,
+        systems: &TopologicalGraph<&'s SystemInfo>,
+        visited: &mut HashSet<&'s SystemInfo>,
+        exclusive_dependencies: &mut HashSet<usize>,
+    ) {
+        for &dependency in node.dependencies() {
+            if !visited.insert(dependency) {
+                continue;
+            }
+            if dependency.is_exclusive() {
+                if let Some(&system_index) = info_to_index.get(dependency) {
+                    exclusive_dependencies.insert(system_index);
+                }
+            }
+            let dependency_node = systems.node(&dependency).unwrap();
+            Self::collect_exclusive_dependencies(
+                dependency_node,
+                info_to_index,
+                systems,
+                visited,
+                exclusive_dependencies,
+            );
+        }
+    }
+
     fn add_dependants(
         &self,
         dependant_index: usize,
         node: &Node<&SystemInfo>,
         info_to_index: &HashMap<&SystemInfo, usize>,
+        system_stages: &[usize],
         systems: &TopologicalGraph<&SystemInfo>,
     ) {
         node.dependencies()
             .par_iter()
             .for_each(|dependency: &&SystemInfo| {
                 if let Some(&system_index) = info_to_index.get(dependency) {
-                    self.dependants[system_index].insert(dependant_index);
+                    // Dependencies in former stages are guaranteed by the stage barrier.
+                    if !dependency.is_exclusive()
+                        && system_stages[system_index] == system_stages[dependant_index]
+                    {
+                        self.dependants[system_index].insert(dependant_index);
+                    }
                 } else {
                     let dependency_node = systems.node(dependency).unwrap();
-                    self.add_dependants(dependant_index, dependency_node, info_to_index, systems);
+                    self.add_dependants(
+                        dependant_index,
+                        dependency_node,
+                        info_to_index,
+                        system_stages,
+                        systems,
+                    );
                 }
             });
     }
 }
 
+#[derive(Default)]
+struct Stage {
+    systems: Vec<usize>,
+    exclusive: Option<usize>,
+}
+
 struct RunnableCell(UnsafeCell<Box<dyn RunnableSystem>>);
 
 impl RunnableCell {
@@ -140,25 +325,68 @@ impl RunnableCell {
     pub(crate) fn get_mut(&self) -> &mut dyn RunnableSystem {
         (unsafe { &mut *self.0.get() }).deref_mut()
     }
+
+    fn into_inner(self) -> Box<dyn RunnableSystem> {
+        self.0.into_inner()
+    }
 }
 
 unsafe impl Sync for RunnableCell {}
 
 pub trait RunnableSystem: Send + Sync {
+    fn setup(&mut self, world: &mut World);
+
     /// Run system
     ///
     /// # Safety
     ///
     /// Access to a resource can only have multiple reads or one write at the same time
     unsafe fn run(&mut self, world: &World);
+
+    fn run_exclusive(&mut self, world: &mut World) {
+        unsafe { self.run(world) }
+    }
+
+    fn teardown(&mut self, world: &mut World);
 }
 
 impl<T> RunnableSystem for T
 where
     for<'r> T: System<'r> + Send + Sync,
 {
+    fn setup(&mut self, world: &mut World) {
+        <T as System<'_>>::setup(self, world);
+    }
+
     unsafe fn run(&mut self, world: &World) {
-        self.run(T::SystemData::fetch(world));
+        <T as System<'_>>::run(self, T::SystemData::fetch(world));
+    }
+
+    fn teardown(&mut self, world: &mut World) {
+        <T as System<'_>>::teardown(self, world);
+    }
+}
+
+pub(crate) struct ExclusiveRunner<S>(pub(crate) S);
+
+impl<S> RunnableSystem for ExclusiveRunner<S>
+where
+    for<'r> S: ExclusiveSystem<'r> + Send + Sync,
+{
+    fn setup(&mut self, world: &mut World) {
+        <S as ExclusiveSystem<'_>>::setup(&mut self.0, world);
+    }
+
+    unsafe fn run(&mut self, _world: &World) {
+        panic!("Exclusive system must be run with `run_exclusive`");
+    }
+
+    fn run_exclusive(&mut self, world: &mut World) {
+        <S as ExclusiveSystem<'_>>::run(&mut self.0, world);
+    }
+
+    fn teardown(&mut self, world: &mut World) {
+        <S as ExclusiveSystem<'_>>::teardown(&mut self.0, world);
     }
 }
 
@@ -196,4 +424,114 @@ mod tests {
             assert_eq!(other.value, 100);
         }
     }
+
+    #[system]
+    struct SetupSystem {}
+
+    #[derive(Default)]
+    struct SetupResource {
+        setup_count: i32,
+        run_count: i32,
+    }
+
+    impl<'r> System<'r> for SetupSystem {
+        type SystemData = Write<'r, SetupResource>;
+
+        fn setup(&mut self, world: &mut World) {
+            world.insert(SetupResource::default).setup_count += 1;
+        }
+
+        fn run(&mut self, mut system_data: Self::SystemData) {
+            system_data.run_count += 1;
+        }
+    }
+
+    #[test]
+    fn setup_system() {
+        let mut world = World::default();
+        let mut scheduler = Scheduler::new(&mut world);
+        scheduler.update(&mut world);
+        scheduler.update(&mut world);
+        let resource = unsafe { world.fetch::<SetupResource>() };
+        assert_eq!(resource.setup_count, 1);
+        assert_eq!(resource.run_count, 2);
+    }
+
+    #[system]
+    struct TeardownSystem {}
+
+    #[derive(Default)]
+    struct TeardownResource {
+        setup_count: i32,
+        teardown_count: i32,
+    }
+
+    impl<'r> System<'r> for TeardownSystem {
+        type SystemData = Write<'r, TeardownResource>;
+
+        fn setup(&mut self, world: &mut World) {
+            // deadlocks if the scheduler still holds the registry lock
+            let _sr = SystemRegistry::instance();
+            world.insert(TeardownResource::default).setup_count += 1;
+        }
+
+        fn run(&mut self, _system_data: Self::SystemData) {}
+
+        fn teardown(&mut self, world: &mut World) {
+            world.insert(TeardownResource::default).teardown_count += 1;
+        }
+    }
+
+    #[test]
+    fn teardown_on_shutdown() {
+        let mut world = World::default();
+        let mut scheduler = Scheduler::new(&mut world);
+        scheduler.update(&mut world);
+        scheduler.shutdown(&mut world);
+        scheduler.update(&mut world);
+        let resource = unsafe { world.fetch::<TeardownResource>() };
+        assert_eq!(resource.setup_count, 1);
+        assert_eq!(resource.teardown_count, 1);
+    }
+
+    #[derive(Default)]
+    struct ExclusiveResource {
+        records: Vec<&'static str>,
+    }
+
+    #[system]
+    struct BeforeExclusiveSystem {}
+
+    impl<'r> System<'r> for BeforeExclusiveSystem {
+        type SystemData = Write<'r, ExclusiveResource>;
+
+        fn run(&mut self, mut system_data: Self::SystemData) {
+            system_data.records.push("before");
+        }
+    }
+
+    #[system(exclusive)]
+    struct TestExclusiveSystem {}
+
+    impl<'r> ExclusiveSystem<'r> for TestExclusiveSystem {
+        type SystemData = RAW<'r, ExclusiveResource>;
+
+        fn run(&mut self, world: &mut World) {
+            world.create_entity().create();
+            let resource = unsafe { world.fetch_mut::<ExclusiveResource>() };
+            resource.records.push("exclusive");
+        }
+    }
+
+    #[test]
+    fn exclusive_system() {
+        let mut world = World::default();
+        world.insert(ExclusiveResource::default);
+        let mut scheduler = Scheduler::new(&mut world);
+        scheduler.update(&mut world);
+        let resource = unsafe { world.fetch::<ExclusiveResource>() };
+        assert_eq!(resource.records, vec!["before", "exclusive"]);
+        let entities = unsafe { world.fetch::<Entities>() };
+        assert_eq!(entities.len(), 1);
+    }
 }
@@ -159,3 +159,36 @@ macro_rules! impl_system_data_tuple {
 }
 
 impl_system_data_tuple!(S0, S1, S2, S3, S4, S5, S6, S7, S8, S9, S10, S11, S12, S13, S14, S15);
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    struct ResourceA {
+        value: i32,
+    }
+
+    struct ResourceB {
+        value: i32,
+    }
+
+    #[derive(SystemData)]
+    struct TestData<'r> {
+        a: RBW<'r, ResourceA>,
+        b: Write<'r, ResourceB>,
+    }
+
+    #[test]
+    fn derive_system_data() {
+        assert!(TestData::reads_before_write() == vec![ResourceId::new::<ResourceA>()]);
+        assert!(TestData::writes() == vec![ResourceId::new::<ResourceB>()]);
+        assert!(TestData::reads_after_write().is_empty());
+
+        let mut world = World::default();
+        world.insert(|| ResourceA { value: 1 });
+        world.insert(|| ResourceB { value: 2 });
+        let mut data = unsafe { TestData::fetch(&world) };
+        data.b.value += data.a.value;
+        assert_eq!(unsafe { world.fetch::<ResourceB>() }.value, 3);
+    }
+}
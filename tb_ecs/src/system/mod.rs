@@ -1,12 +1,31 @@
 pub use data::*;
 pub use registry::*;
 
+use crate::World;
+
 mod data;
 mod registry;
 
 pub trait System<'r>: Send {
     type SystemData: SystemData<'r>;
+
+    /// Called once by the `Scheduler` after the system instance is created.
+    /// Register the resources the system needs or cache data from the world here.
+    fn setup(&mut self, _world: &mut World) {}
     fn run(&mut self, system_data: Self::SystemData);
+    /// Called once by the `Scheduler` before the system instance is destroyed.
+    fn teardown(&mut self, _world: &mut World) {}
+}
+
+/// A system that needs structural access to the whole world.
+/// The `Scheduler` runs it alone at a barrier between the other systems.
+pub trait ExclusiveSystem<'r>: Send {
+    /// Only used to order the system among other systems, it is never fetched.
+    type SystemData: SystemData<'r>;
+
+    fn setup(&mut self, _world: &mut World) {}
+    fn run(&mut self, world: &mut World);
+    fn teardown(&mut self, _world: &mut World) {}
 }
 
 #[cfg(test)]
@@ -4,16 +4,31 @@ use std::hash::{Hash, Hasher};
 use std::lazy::SyncLazy;
 use std::sync::{Mutex, MutexGuard};
 
+use errors::*;
 use tb_core::algorithm::topological_sort::TopologicalGraph;
 use tb_core::event_channel::{EventChannel, ReaderHandle};
 use tb_core::*;
 
-use crate::scheduler::RunnableSystem;
+use crate::scheduler::{ExclusiveRunner, RunnableSystem};
 use crate::world::ResourceId;
-use crate::{System, SystemData, World};
+use crate::{ExclusiveSystem, System, SystemData, World};
+
+mod errors {
+    pub use tb_core::error::*;
+
+    error_chain! {
+        errors {
+            NameCollision(name: String) {
+                description("System name is registered by another system"),
+                display("System name is registered by another system. name: {}", name),
+            }
+        }
+    }
+}
 
 pub struct SystemRegistry {
-    systems: HashMap<TypeId, &'static SystemInfo>,
+    systems: HashMap<String, &'static SystemInfo>,
+    owners: HashMap<String, String>,
     resources_info: HashMap<ResourceId, ResourceInfo>,
     system_topological_graph:
         tb_core::algorithm::topological_sort::TopologicalGraph<&'static SystemInfo>,
@@ -28,16 +43,19 @@ impl SystemRegistry {
             let system_changed_reader = system_changed_events.register();
             let mut registry = SystemRegistry {
                 systems: Default::default(),
+                owners: Default::default(),
                 resources_info: Default::default(),
                 system_topological_graph: Default::default(),
                 system_changed_events,
                 system_changed_reader,
             };
 
-            for system_info in inventory::iter::<SystemInfo> {
-                registry
-                    .systems
-                    .insert(system_info.system_type_id(), system_info);
+            let infos: Vec<_> = inventory::iter::<SystemInfo>.into_iter().collect();
+            if let Err(e) = registry.check_collisions(None, &infos) {
+                panic!("{}", e);
+            }
+            for info in infos {
+                registry.insert(None, info);
             }
 
             registry.system_changed_events.push(());
@@ -47,14 +65,89 @@ impl SystemRegistry {
         SYSTEM_REGISTRY.lock().unwrap()
     }
 
-    pub fn add_system_infos(infos: Box<dyn Iterator<Item = &'static SystemInfo>>) {
+    /// Register the system infos of `owner`, e.g. a plugin.
+    /// An info replaces the registered one with the same name if both are registered by
+    /// the same owner, e.g. when a plugin is reloaded. The same type registered by the engine
+    /// or another owner is shared, and stays theirs. Otherwise the name collides,
+    /// and nothing is registered.
+    pub fn add_system_infos(
+        owner: &str,
+        infos: Box<dyn Iterator<Item = &'static SystemInfo>>,
+    ) -> Result<()> {
+        let infos: Vec<_> = infos.collect();
         let mut sr = Self::instance();
-        let sr = &mut sr;
+        sr.check_collisions(Some(owner), &infos)?;
+        sr.system_changed_events.push(());
+        for info in infos {
+            if !sr.is_owned_by_other(owner, info) {
+                sr.insert(Some(owner), info);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the same type is registered under the name by the engine or another owner
+    fn is_owned_by_other(&self, owner: &str, info: &SystemInfo) -> bool {
+        match self.systems.get(info.name) {
+            Some(registered) => {
+                registered.type_id == info.type_id
+                    && self.owners.get(info.name).map(String::as_str) != Some(owner)
+            }
+            None => false,
+        }
+    }
+
+    /// Unregister the systems of `owner`, e.g. before the plugin is unloaded.
+    /// The instances are dropped by the next `Scheduler::refresh_systems`.
+    pub fn remove_owner(owner: &str) {
+        let mut sr = Self::instance();
+        let names: Vec<String> = sr
+            .owners
+            .iter()
+            .filter(|(_name, o)| o.as_str() == owner)
+            .map(|(name, _owner)| name.clone())
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        for name in names {
+            sr.systems.remove(&name);
+            sr.owners.remove(&name);
+        }
         sr.system_changed_events.push(());
-        let systems = &mut sr.systems;
+    }
+
+    /// Find the info of a system by its stable name
+    pub fn info_by_name(&self, name: &str) -> Option<&'static SystemInfo> {
+        self.systems.get(name).copied()
+    }
+
+    fn check_collisions(&self, owner: Option<&str>, infos: &[&'static SystemInfo]) -> Result<()> {
+        let mut names = HashMap::with_capacity(infos.len());
         for info in infos {
-            systems.insert(info.system_type_id(), info);
+            if let Some(other) = names.insert(info.name, info.type_id) {
+                if other != info.type_id {
+                    bail!(ErrorKind::NameCollision(info.name.to_owned()));
+                }
+            }
+            if let Some(registered) = self.systems.get(info.name) {
+                let same_type = registered.type_id == info.type_id;
+                let same_owner =
+                    owner.is_some() && self.owners.get(info.name).map(String::as_str) == owner;
+                if !same_type && !same_owner {
+                    bail!(ErrorKind::NameCollision(info.name.to_owned()));
+                }
+            }
         }
+        Ok(())
+    }
+
+    fn insert(&mut self, owner: Option<&str>, info: &'static SystemInfo) {
+        self.systems.insert(info.name.to_owned(), info);
+        match owner {
+            None => self.owners.remove(info.name),
+            Some(owner) => self.owners.insert(info.name.to_owned(), owner.to_owned()),
+        };
     }
 
     pub fn systems(&mut self) -> &TopologicalGraph<&'static SystemInfo> {
@@ -151,6 +244,7 @@ pub struct ResourceInfo {
 pub struct SystemInfo {
     type_id: TypeId,
     name: &'static str,
+    exclusive: bool,
     reads_before_write: Vec<ResourceId>,
     reads_after_write: Vec<ResourceId>,
     writes: Vec<ResourceId>,
@@ -162,6 +256,35 @@ impl SystemInfo {
     where
         for<'r> S: 'static + std::default::Default + System<'r> + Sync,
     {
+        Self::build::<S>(
+            false,
+            S::SystemData::reads_before_write(),
+            S::SystemData::reads_after_write(),
+            S::SystemData::writes(),
+            || Box::new(S::default()),
+        )
+    }
+
+    pub fn new_exclusive<S>() -> Self
+    where
+        for<'r> S: 'static + std::default::Default + ExclusiveSystem<'r> + Sync,
+    {
+        Self::build::<S>(
+            true,
+            S::SystemData::reads_before_write(),
+            S::SystemData::reads_after_write(),
+            S::SystemData::writes(),
+            || Box::new(ExclusiveRunner(S::default())),
+        )
+    }
+
+    fn build<S: 'static>(
+        exclusive: bool,
+        reads_before_write: Vec<ResourceId>,
+        reads_after_write: Vec<ResourceId>,
+        writes: Vec<ResourceId>,
+        create: fn() -> Box<dyn RunnableSystem>,
+    ) -> Self {
         let type_id = std::any::TypeId::of::<S>();
         let name = std::any::type_name::<S>();
         println!(
@@ -172,13 +295,20 @@ impl SystemInfo {
         Self {
             type_id,
             name,
-            reads_before_write: S::SystemData::reads_before_write(),
-            reads_after_write: S::SystemData::reads_after_write(),
-            writes: S::SystemData::writes(),
-            create: || Box::new(S::default()),
+            exclusive,
+            reads_before_write,
+            reads_after_write,
+            writes,
+            create,
         }
     }
 
+    /// The name identifying the system across plugin builds
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
     pub fn name(&self) -> &str {
         self.name
     }
@@ -187,6 +317,10 @@ impl SystemInfo {
         self.type_id
     }
 
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
     pub fn is_resources_existed(&self, world: &World) -> bool {
         self.reads_after_write
             .par_iter()
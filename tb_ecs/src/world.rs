@@ -68,6 +68,15 @@ impl World {
         unsafe { res.get_mut::<R>() }
     }
 
+    /// Drop the resource, returns whether it existed
+    pub fn remove<R: Resource>(&mut self) -> bool {
+        let removed = self.resources.remove(&ResourceId::new::<R>()).is_some();
+        if removed {
+            self.resource_change_events.push(ResourceChangeEvent::new());
+        }
+        removed
+    }
+
     /// Fetch immutable resource
     ///
     /// # Safety
@@ -182,6 +191,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn remove_resource() {
+        let mut world = World::default();
+        assert!(!world.remove::<TestResource>());
+        world.insert(|| TestResource::new(10));
+        assert!(world.remove::<TestResource>());
+        assert!(!world.contains::<TestResource>());
+    }
+
     #[test]
     #[should_panic(expected = "Error(Fetch(\"tb_ecs::world::tests::TestResource\")")]
     fn fetch_resource_failed() {
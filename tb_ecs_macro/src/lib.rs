@@ -1,99 +1,785 @@
-#![feature(exact_size_is_empty)]
-
 use proc_macro::TokenStream;
 
 use quote::*;
 use syn::*;
 
 #[proc_macro_attribute]
-pub fn system(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn system(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = parse_macro_input!(attr as AttributeArgs);
     let system_struct = parse_macro_input!(item as ItemStruct);
     let system_name = &system_struct.ident;
+    let mut exclusive = false;
+    let mut name = default_name(system_name);
+    for arg in &attr {
+        match arg {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("exclusive") => {
+                exclusive = true;
+            }
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(lit),
+                ..
+            })) if path.is_ident("name") => {
+                name = quote! { #lit };
+            }
+            _ => {
+                return Error::new_spanned(arg, "unknown system attribute")
+                    .to_compile_error()
+                    .into();
+            }
+        }
+    }
+
+    let system_info = if exclusive {
+        quote! { SystemInfo::new_exclusive::<#system_name>().with_name(#name) }
+    } else {
+        quote! { SystemInfo::new::<#system_name>().with_name(#name) }
+    };
     let output = quote! {
         #[derive(Default)]
         #system_struct
         inventory::submit! {
-            SystemInfo::new::<#system_name>()
+            #system_info
         }
     };
     output.into()
 }
 
+/// Implement `Component` and register it.
+///
+/// Fields of `Entity`, and of options, collections, map values and tuples of them, are treated
+/// as entity references. Map keys are not. Tuple elements without entities are left alone.
+/// Mark other fields (type aliases, generic parameters) with `#[entity_ref]`,
+/// and opt a field out with `#[entity_ref(skip)]`.
+/// What happens when the referenced entity is killed is opt-in per field:
+/// * `#[entity_ref(nullify)]` drops the dead reference from an `Option` or a collection,
+///   the owning component is removed if the reference can't be dropped.
+/// * `#[entity_ref(remove)]` removes the owning component.
+/// * `#[entity_ref(cascade)]` kills the owner.
+///
+/// The component is registered with its module path and type name,
+/// `#[component(name = "...")]` overrides the name.
+///
+/// Generic components are not registered, submit a `ComponentInfo` for every instantiation.
+/// Use `ComponentInfo::with_entity_refs` for them when they reference entities.
 #[proc_macro_attribute]
-pub fn component(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let component_struct = parse_macro_input!(item as ItemStruct);
-    let fields: Vec<&Field> = component_struct
-        .fields
+pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = parse_macro_input!(attr as AttributeArgs);
+    let mut name = None;
+    let mut indexed_fields = vec![];
+    for arg in &attr {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(lit),
+                ..
+            })) if path.is_ident("name") => {
+                name = Some(quote! { #lit });
+            }
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(lit),
+                ..
+            })) if path.is_ident("index") => match lit.parse::<Member>() {
+                Ok(member) => indexed_fields.push((lit.value(), member)),
+                Err(e) => return e.to_compile_error().into(),
+            },
+            _ => {
+                return Error::new_spanned(arg, "unknown component attribute")
+                    .to_compile_error()
+                    .into();
+            }
+        }
+    }
+    let mut item = parse_macro_input!(item as Item);
+    let (component_name, generics, groups) = match &mut item {
+        Item::Struct(item_struct) => {
+            let fields = match take_entity_ref_fields(&mut item_struct.fields) {
+                Ok(fields) => fields,
+                Err(e) => return e.to_compile_error().into(),
+            };
+            let fields = fields
+                .into_iter()
+                .map(|field| {
+                    let member = &field.member;
+                    (quote! { &mut self.#member }, field)
+                })
+                .collect();
+            let groups = vec![EntityRefGroup {
+                pattern: None,
+                fields,
+            }];
+            (
+                item_struct.ident.clone(),
+                item_struct.generics.clone(),
+                groups,
+            )
+        }
+        Item::Enum(item_enum) => {
+            let mut groups = vec![];
+            for variant in &mut item_enum.variants {
+                let variant_name = &variant.ident;
+                let fields = match take_entity_ref_fields(&mut variant.fields) {
+                    Ok(fields) => fields,
+                    Err(e) => return e.to_compile_error().into(),
+                };
+                if fields.is_empty() {
+                    continue;
+                }
+                let bindings: Vec<Ident> = fields
+                    .iter()
+                    .map(|field| match &field.member {
+                        Member::Named(ident) => format_ident!("__{}", ident),
+                        Member::Unnamed(index) => format_ident!("__{}", index.index),
+                    })
+                    .collect();
+                let pattern = match &variant.fields {
+                    Fields::Named(_) => {
+                        let members = fields.iter().map(|field| &field.member);
+                        quote! { Self::#variant_name { #(#members: #bindings),*, .. } }
+                    }
+                    Fields::Unnamed(unnamed) => {
+                        let elements = (0..unnamed.unnamed.len()).map(|index| {
+                            match fields
+                                .iter()
+                                .position(|field| field.member == Member::from(index))
+                            {
+                                Some(position) => {
+                                    let binding = &bindings[position];
+                                    quote! { #binding }
+                                }
+                                None => quote! { _ },
+                            }
+                        });
+                        quote! { Self::#variant_name(#(#elements),*) }
+                    }
+                    Fields::Unit => unreachable!(),
+                };
+                let fields = bindings
+                    .into_iter()
+                    .zip(fields)
+                    .map(|(binding, field)| (quote! { &mut *#binding }, field))
+                    .collect();
+                groups.push(EntityRefGroup {
+                    pattern: Some(pattern),
+                    fields,
+                });
+            }
+            (item_enum.ident.clone(), item_enum.generics.clone(), groups)
+        }
+        item => {
+            return Error::new_spanned(item, "component must be a struct or an enum")
+                .to_compile_error()
+                .into();
+        }
+    };
+    if !indexed_fields.is_empty() && !matches!(item, Item::Struct(_)) {
+        return Error::new_spanned(&component_name, "only fields of structs can be indexed")
+            .to_compile_error()
+            .into();
+    }
+
+    let entity_ref_types: Vec<&Type> = groups
         .iter()
-        .filter(|field| {
-            let ty = field.ty.to_token_stream().to_string();
-            ty == "Entity" || ty == "Vec<Entity>"
-        })
+        .flat_map(|group| group.fields.iter())
+        .flat_map(|(_, field)| field.entity_ref_types())
         .collect();
+    let has_policy = groups
+        .iter()
+        .any(|group| group.fields.iter().any(|(_, field)| field.policy.is_some()));
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    let impl_component_with_entity_ref = if entity_ref_types.is_empty() {
+        quote! {}
+    } else {
+        let mut entity_ref_generics = generics.clone();
+        {
+            let where_clause = entity_ref_generics.make_where_clause();
+            for ty in &entity_ref_types {
+                where_clause
+                    .predicates
+                    .push(parse_quote! { #ty: EntityRef });
+            }
+        }
+        let (_, _, entity_ref_where_clause) = entity_ref_generics.split_for_impl();
 
-    let component_name = &component_struct.ident;
-
-    let impl_component_with_entity_ref = {
-        let mut fields = fields.iter();
-        match fields.next() {
-            None => {
-                quote! {}
-            }
-            Some(&first) => {
-                let (ref_ident, ref_type): (proc_macro2::TokenStream, proc_macro2::TokenStream) = {
-                    let (first_ref_ident, first_ref_type) = {
-                        let ident = &first.ident;
-                        let ty = &first.ty;
-                        (quote! {&mut self.#ident}, quote! {&'e mut #ty})
-                    };
-
-                    if !fields.is_empty() {
-                        let (mut ref_ident, mut ref_type) = (
-                            format!("({}", first_ref_ident),
-                            format!("({}", first_ref_type),
-                        );
-
-                        for &field in fields {
-                            ref_type +=
-                                &format!(", &'e mut {}", field.ty.to_token_stream().to_string());
-                            ref_ident += &format!(
-                                ", &mut self.{}",
-                                field.ident.to_token_stream().to_string()
-                            );
-                        }
-                        ref_ident += ")";
-                        ref_type += ")";
-
-                        (ref_ident.parse().unwrap(), ref_type.parse().unwrap())
-                    } else {
-                        (first_ref_ident, first_ref_type)
+        let mut with_entity_ref_generics = entity_ref_generics.clone();
+        with_entity_ref_generics
+            .params
+            .insert(0, parse_quote! { 'e });
+        with_entity_ref_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote! { Self: Component });
+        let (with_entity_ref_impl_generics, _, with_entity_ref_where_clause) =
+            with_entity_ref_generics.split_for_impl();
+
+        let for_each_body = visit_entity_refs(&groups, |access, _field| {
+            Some(quote! { EntityRef::for_each(#access, action); })
+        });
+        let retain_body = visit_entity_refs(&groups, |access, _field| {
+            Some(quote! { kept = EntityRef::retain(#access, keep) && kept; })
+        });
+
+        let impl_clean_dangling_entity_ref = if has_policy {
+            let on_entity_killed_body = visit_entity_refs(&groups, |access, field| {
+                let dangling_action = match field.policy? {
+                    KillPolicy::Nullify => {
+                        return Some(quote! {
+                            if !EntityRef::retain(#access, &mut |entity: &Entity| *entity != killed) {
+                                action = action.max(DanglingAction::RemoveComponent);
+                            }
+                        });
                     }
+                    KillPolicy::Remove => quote! { DanglingAction::RemoveComponent },
+                    KillPolicy::Cascade => quote! { DanglingAction::KillOwner },
                 };
-                quote! {
-                    impl<'e> ComponentWithEntityRef<'e> for #component_name {
-                        type Ref = #ref_type;
+                Some(quote! {
+                    let mut found = false;
+                    EntityRef::for_each(#access, &mut |entity: &mut Entity| found |= *entity == killed);
+                    if found {
+                        action = action.max(#dangling_action);
+                    }
+                })
+            });
+            let for_each_policy_ref_body = visit_entity_refs(&groups, |access, field| {
+                field.policy?;
+                Some(quote! { EntityRef::for_each(#access, action); })
+            });
+            quote! {
+                impl #impl_generics CleanDanglingEntityRef for #component_name #ty_generics #with_entity_ref_where_clause {
+                    fn on_entity_killed(&mut self, killed: Entity) -> DanglingAction {
+                        let mut action = DanglingAction::Keep;
+                        #on_entity_killed_body
+                        action
+                    }
 
-                        fn mut_entity_ref(&'e mut self) -> Self::Ref {
-                            #ref_ident
-                        }
+                    fn for_each_policy_ref(&mut self, action: &mut impl FnMut(&mut Entity)) {
+                        #for_each_policy_ref_body
                     }
                 }
             }
+        } else {
+            quote! {}
+        };
+
+        quote! {
+            impl #impl_generics EntityRef for #component_name #ty_generics #entity_ref_where_clause {
+                fn for_each(&mut self, action: &mut impl FnMut(&mut Entity)) {
+                    #for_each_body
+                }
+
+                fn retain(&mut self, keep: &mut impl FnMut(&Entity) -> bool) -> bool {
+                    let mut kept = true;
+                    #retain_body
+                    kept
+                }
+            }
+
+            impl #with_entity_ref_impl_generics ComponentWithEntityRef<'e> for #component_name #ty_generics
+                #with_entity_ref_where_clause
+            {
+                type Ref = &'e mut Self;
+
+                fn mut_entity_ref(&'e mut self) -> Self::Ref {
+                    self
+                }
+            }
+
+            #impl_clean_dangling_entity_ref
         }
     };
 
+    let register = if generics.params.is_empty() {
+        let with_entity_refs = if entity_ref_types.is_empty() {
+            quote! {}
+        } else {
+            quote! { .with_entity_refs::<#component_name>() }
+        };
+        let with_dangling_policy = if has_policy {
+            quote! { .with_dangling_policy::<#component_name>() }
+        } else {
+            quote! {}
+        };
+        let name = name.unwrap_or_else(|| default_name(&component_name));
+        quote! {
+            inventory::submit! {
+                ComponentInfo::new::<#component_name>()
+                    .with_name(#name)
+                    .with_reflect::<#component_name>()
+                    .with_json::<#component_name>()
+                    #with_entity_refs
+                    #with_dangling_policy
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let mut component_where_clause = generics.clone();
+    component_where_clause
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { Self: 'static + Send + Sync + SerdeBoxSer + SerdeBoxDe });
+    let (_, _, component_where_clause) = component_where_clause.split_for_impl();
+
+    let index_keys = if indexed_fields.is_empty() {
+        quote! {}
+    } else {
+        let keys = indexed_fields
+            .iter()
+            .map(|(field, member)| quote! { (#field, index_key(&self.#member)) });
+        quote! {
+            fn index_keys(&self) -> Vec<(&'static str, IndexKey)> {
+                vec![#(#keys),*]
+            }
+
+            fn has_index() -> bool {
+                true
+            }
+        }
+    };
+
+    let has_kill_policy = if has_policy {
+        quote! {
+            fn has_kill_policy() -> bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // statics in generic functions are shared by all instantiations
+    let index_cache = if generics.params.is_empty() {
+        quote! {
+            fn index_cache() -> Option<&'static ComponentIndexCache> {
+                static CACHE: ComponentIndexCache = ComponentIndexCache::new();
+                Some(&CACHE)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let impl_reflect = impl_reflect(&item);
+
     let output = quote! {
         #[derive(Clone, Deserialize, Serialize)]
-        #component_struct
+        #item
 
-        impl Component for #component_name {}
+        impl #impl_generics Component for #component_name #ty_generics #component_where_clause {
+            #index_keys
+            #index_cache
+            #has_kill_policy
+        }
 
         #impl_component_with_entity_ref
 
-        inventory::submit! {
-            ComponentInfo::new::<#component_name>()
+        #impl_reflect
+
+        #register
+    };
+
+    output.into()
+}
+
+/// Implement `Reflect` through the serialization of the fields,
+/// only the value itself can be accessed for enums.
+fn impl_reflect(item: &Item) -> proc_macro2::TokenStream {
+    let (component_name, generics, fields) = match item {
+        Item::Struct(item_struct) => (
+            &item_struct.ident,
+            &item_struct.generics,
+            item_struct.fields.iter().collect(),
+        ),
+        Item::Enum(item_enum) => (&item_enum.ident, &item_enum.generics, vec![]),
+        _ => unreachable!(),
+    };
+    let members: Vec<Member> = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::from(index),
+        })
+        .collect();
+    let names: Vec<LitStr> = members
+        .iter()
+        .map(|member| match member {
+            Member::Named(ident) => LitStr::new(&ident.to_string(), ident.span()),
+            Member::Unnamed(index) => LitStr::new(&index.index.to_string(), index.span),
+        })
+        .collect();
+    let types: Vec<&Type> = fields.iter().map(|field| &field.ty).collect();
+
+    let mut reflect_generics = generics.clone();
+    if !generics.params.is_empty() {
+        let predicates = &mut reflect_generics.make_where_clause().predicates;
+        predicates.push(parse_quote! { Self: Serialize + for<'de> Deserialize<'de> });
+        for ty in &types {
+            predicates.push(parse_quote! { #ty: Serialize + for<'de> Deserialize<'de> });
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = reflect_generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics Reflect for #component_name #ty_generics #where_clause {
+            fn fields() -> Vec<FieldInfo> {
+                vec![#(
+                    FieldInfo::new::<#types>(#names, (&ReflectProbe::<#types>::new()).nested_fields())
+                ),*]
+            }
+
+            fn reflect_fields(&self) -> Vec<FieldInfo> {
+                Self::fields()
+            }
+
+            fn get_field(&self, path: &str) -> ReflectResult<serde_json::Value> {
+                match split_field_path(path) {
+                    ("", _) => reflect_get(self, path),
+                    #((#names, rest) => reflect_get(&self.#members, rest),)*
+                    _ => Err(unknown_field_error(path)),
+                }
+            }
+
+            fn set_field(&mut self, path: &str, value: serde_json::Value) -> ReflectResult<()> {
+                match split_field_path(path) {
+                    ("", _) => reflect_set(self, path, value),
+                    #((#names, rest) => reflect_set(&mut self.#members, rest, value),)*
+                    _ => Err(unknown_field_error(path)),
+                }
+            }
+        }
+    }
+}
+
+/// The module path and type name, which are stable across builds unlike `TypeId`
+fn default_name(ident: &Ident) -> proc_macro2::TokenStream {
+    quote! { concat!(module_path!(), "::", stringify!(#ident)) }
+}
+
+#[derive(Copy, Clone)]
+enum KillPolicy {
+    Nullify,
+    Remove,
+    Cascade,
+}
+
+struct EntityRefField {
+    member: Member,
+    ty: Type,
+    /// The visited elements of a tuple field with other elements, the whole field if empty
+    elements: Vec<(Index, Type)>,
+    policy: Option<KillPolicy>,
+}
+
+impl EntityRefField {
+    /// The types required to be `EntityRef`
+    fn entity_ref_types(&self) -> Vec<&Type> {
+        if self.elements.is_empty() {
+            vec![&self.ty]
+        } else {
+            self.elements.iter().map(|(_index, ty)| ty).collect()
+        }
+    }
+
+    /// The mutable references to the visited values, from the one to the field
+    fn accesses(&self, access: &proc_macro2::TokenStream) -> Vec<proc_macro2::TokenStream> {
+        if self.elements.is_empty() {
+            vec![access.clone()]
+        } else {
+            self.elements
+                .iter()
+                .map(|(index, _ty)| quote! { &mut (#access).#index })
+                .collect()
+        }
+    }
+}
+
+/// Entity references of a struct, or of an enum variant matched by `pattern`
+struct EntityRefGroup {
+    pattern: Option<proc_macro2::TokenStream>,
+    fields: Vec<(proc_macro2::TokenStream, EntityRefField)>,
+}
+
+fn visit_entity_refs(
+    groups: &[EntityRefGroup],
+    visit: impl Fn(&proc_macro2::TokenStream, &EntityRefField) -> Option<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let visit = &visit;
+    let mut arms = vec![];
+    for group in groups {
+        let statements: Vec<proc_macro2::TokenStream> = group
+            .fields
+            .iter()
+            .flat_map(|(access, field)| {
+                field
+                    .accesses(access)
+                    .into_iter()
+                    .filter_map(move |access| visit(&access, field))
+            })
+            .collect();
+        match &group.pattern {
+            None => return quote! { #(#statements)* },
+            Some(pattern) => {
+                if !statements.is_empty() {
+                    arms.push(quote! { #pattern => { #(#statements)* } });
+                }
+            }
+        }
+    }
+    if arms.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #[allow(unreachable_patterns)]
+            match self {
+                #(#arms)*
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Remove the `#[entity_ref]` attributes and return the fields referencing entities.
+fn take_entity_ref_fields(fields: &mut Fields) -> Result<Vec<EntityRefField>> {
+    let mut entity_ref_fields = vec![];
+    for (index, field) in fields.iter_mut().enumerate() {
+        let mut marked = false;
+        let mut skipped = false;
+        let mut policy = None;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("entity_ref"))
+        {
+            marked = true;
+            match attr.parse_meta()? {
+                Meta::Path(_) => {}
+                Meta::List(list) if list.nested.len() == 1 => match &list.nested[0] {
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                        skipped = true;
+                    }
+                    nested => {
+                        policy = Some(match nested {
+                            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("nullify") => {
+                                KillPolicy::Nullify
+                            }
+                            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("remove") => {
+                                KillPolicy::Remove
+                            }
+                            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("cascade") => {
+                                KillPolicy::Cascade
+                            }
+                            nested => {
+                                return Err(Error::new_spanned(
+                                    nested,
+                                    "expected `skip`, `nullify`, `remove` or `cascade`",
+                                ));
+                            }
+                        });
+                    }
+                },
+                meta => {
+                    return Err(Error::new_spanned(meta, "expected `#[entity_ref(policy)]`"));
+                }
+            }
+        }
+        if skipped && policy.is_some() {
+            return Err(Error::new_spanned(
+                &field.ty,
+                "a skipped entity reference can't have a kill policy",
+            ));
+        }
+        field.attrs.retain(|attr| !attr.path.is_ident("entity_ref"));
+        if skipped {
+            continue;
+        }
+        let elements = if marked || is_entity_ref(&field.ty) {
+            vec![]
+        } else {
+            match entity_ref_elements(&field.ty) {
+                Some(elements) => elements,
+                None => continue,
+            }
+        };
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::from(index),
+        };
+        entity_ref_fields.push(EntityRefField {
+            member,
+            ty: field.ty.clone(),
+            elements,
+            policy,
+        });
+    }
+    Ok(entity_ref_fields)
+}
+
+/// Whether the type references entities in the positions the `EntityRef` impls visit:
+/// the values of options, collections and maps, and tuples of such types.
+/// Map keys are never remapped.
+fn is_entity_ref(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let segment = match type_path.path.segments.last() {
+                Some(segment) => segment,
+                None => return false,
+            };
+            let arguments: Vec<&Type> = match &segment.arguments {
+                PathArguments::None => vec![],
+                PathArguments::AngleBracketed(arguments) => arguments
+                    .args
+                    .iter()
+                    .filter_map(|argument| match argument {
+                        GenericArgument::Type(ty) => Some(ty),
+                        _ => None,
+                    })
+                    .collect(),
+                PathArguments::Parenthesized(_) => return false,
+            };
+            let element = match segment.ident.to_string().as_str() {
+                "Entity" => return arguments.is_empty(),
+                "Option" | "Box" | "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => arguments.first(),
+                "HashMap" | "BTreeMap" => arguments.get(1),
+                _ => None,
+            };
+            matches!(element, Some(element) if is_entity_ref(element))
+        }
+        Type::Array(array) => is_entity_ref(&array.elem),
+        Type::Slice(slice) => is_entity_ref(&slice.elem),
+        Type::Tuple(tuple) => !tuple.elems.is_empty() && tuple.elems.iter().all(is_entity_ref),
+        Type::Paren(paren) => is_entity_ref(&paren.elem),
+        Type::Group(group) => is_entity_ref(&group.elem),
+        _ => false,
+    }
+}
+
+/// The elements of a tuple type referencing entities, if there are some
+fn entity_ref_elements(ty: &Type) -> Option<Vec<(Index, Type)>> {
+    let tuple = match ty {
+        Type::Tuple(tuple) => tuple,
+        Type::Paren(paren) => return entity_ref_elements(&paren.elem),
+        Type::Group(group) => return entity_ref_elements(&group.elem),
+        _ => return None,
+    };
+    let elements: Vec<_> = tuple
+        .elems
+        .iter()
+        .enumerate()
+        .filter(|(_index, ty)| is_entity_ref(ty))
+        .map(|(index, ty)| (Index::from(index), ty.clone()))
+        .collect();
+    if elements.is_empty() {
+        None
+    } else {
+        Some(elements)
+    }
+}
+
+#[proc_macro_derive(SystemData)]
+pub fn derive_system_data(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Error::new_spanned(input, "SystemData can only be derived for named structs")
+                .to_compile_error()
+                .into();
+        }
+    };
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(lifetime_def) => &lifetime_def.lifetime,
+        None => {
+            return Error::new_spanned(
+                &input.generics,
+                "SystemData struct must have a lifetime parameter",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let field_names: Vec<&Ident> = fields
+        .iter()
+        .map(|field| field.ident.as_ref().unwrap())
+        .collect();
+    let field_types: Vec<&Type> = fields.iter().map(|field| &field.ty).collect();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let output = quote! {
+        impl #impl_generics SystemData<#lifetime> for #name #ty_generics #where_clause {
+            unsafe fn fetch(world: &#lifetime World) -> Self {
+                Self {
+                    #(#field_names: <#field_types as SystemData<#lifetime>>::fetch(world)),*
+                }
+            }
+
+            fn reads_before_write() -> Vec<ResourceId> {
+                let mut res = vec![];
+                #(res.append(&mut <#field_types as SystemData<#lifetime>>::reads_before_write());)*
+                res
+            }
+
+            fn writes() -> Vec<ResourceId> {
+                let mut res = vec![];
+                #(res.append(&mut <#field_types as SystemData<#lifetime>>::writes());)*
+                res
+            }
+
+            fn reads_after_write() -> Vec<ResourceId> {
+                let mut res = vec![];
+                #(res.append(&mut <#field_types as SystemData<#lifetime>>::reads_after_write());)*
+                res
+            }
+        }
+    };
+    output.into()
+}
+
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Error::new_spanned(input, "Bundle can only be derived for named structs")
+                .to_compile_error()
+                .into();
         }
     };
 
+    let field_names: Vec<&Ident> = fields
+        .iter()
+        .map(|field| field.ident.as_ref().unwrap())
+        .collect();
+    let field_types: Vec<&Type> = fields.iter().map(|field| &field.ty).collect();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let output = quote! {
+        impl #impl_generics Bundle for #name #ty_generics #where_clause {
+            fn component_indices() -> Vec<ComponentIndex> {
+                vec![#(ComponentIndex::get::<#field_types>()),*]
+            }
+
+            fn reserve_storages(world: &mut World, additional: usize) {
+                #(world.insert_components::<#field_types>().reserve(additional);)*
+            }
+
+            unsafe fn insert_into(self, world: &World, entity: Entity) {
+                #(world.fetch_components_mut::<#field_types>().insert(entity, self.#field_names);)*
+            }
+        }
+    };
     output.into()
 }
@@ -1,11 +1,13 @@
-use std::lazy::SyncLazy;
 use std::path::PathBuf;
 
 use errors::*;
 use tb_core::path_util;
 
+use crate::cli::{Cli, CliCommand, Settings, SettingsOverrides};
+use crate::path::AppDirs;
+
 mod errors {
-    use tb_core::error::*;
+    pub use tb_core::error::*;
 
     error_chain! {}
 }
@@ -16,6 +18,8 @@ pub enum LaunchMethod {
 }
 
 pub struct AppInfo {
+    pub command: CliCommand,
+    pub settings: Settings,
     pub method: LaunchMethod,
     pub engine_root_dir: PathBuf,
     pub engine_assets_dir: PathBuf,
@@ -24,43 +28,46 @@ pub struct AppInfo {
 }
 
 impl AppInfo {
-    pub fn get() -> &'static Self {
-        static INSTANCE: SyncLazy<AppInfo> = SyncLazy::new(|| {
-            let mut method = LaunchMethod::Archive;
-            let mut args = std::env::args();
-            args.next();
-            while let Some(arg) = args.next() {
-                match arg.as_str() {
-                    "--project" | "-p" => {
-                        let project = args.next().unwrap();
-                        method = LaunchMethod::Project {
-                            project_dir: PathBuf::from(project),
-                        }
-                    }
-                    arg => {
-                        eprintln!("unknown argument: {}", arg);
-                    }
-                }
-            }
-
-            let project_root_dir = match &method {
-                LaunchMethod::Project { project_dir } => project_dir.clone(),
-                LaunchMethod::Archive => std::env::current_dir()
-                    .chain_err(|| "Failed to get current_dir")
-                    .unwrap(),
-            };
+    /// Parse the command line, without the program name, and load the project config
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        Self::new(Cli::parse(args).chain_err(|| "Invalid command line")?)
+    }
 
-            let engine_root_dir = path_util::exe_dir();
-            AppInfo {
-                method,
-                engine_assets_dir: engine_root_dir.join(AppInfo::assets_dir_name()),
-                engine_root_dir,
-                project_assets_dir: project_root_dir.join(AppInfo::assets_dir_name()),
-                project_root_dir,
+    /// The project is the one given on the command line, or the current dir
+    pub fn new(cli: Cli) -> Result<Self> {
+        let method = match cli.project_dir {
+            Some(project_dir) => LaunchMethod::Project { project_dir },
+            None => LaunchMethod::Archive,
+        };
+        let project_root_dir = match &method {
+            LaunchMethod::Project { project_dir } => project_dir.clone(),
+            LaunchMethod::Archive => {
+                std::env::current_dir().chain_err(|| "Failed to get current_dir")?
             }
-        });
+        };
+        let config = SettingsOverrides::load_config(&project_root_dir)
+            .chain_err(|| "Failed to load project config")?;
+
+        let engine_root_dir = path_util::exe_dir();
+        Ok(AppInfo {
+            command: cli.command,
+            settings: cli.overrides.or(config).into_settings(),
+            method,
+            engine_assets_dir: engine_root_dir.join(AppInfo::assets_dir_name()),
+            engine_root_dir,
+            project_assets_dir: project_root_dir.join(AppInfo::assets_dir_name()),
+            project_root_dir,
+        })
+    }
 
-        &INSTANCE
+    /// The dirs the `TbPath`s of the app are relative to, e.g. for `AssetLoader::new`
+    pub fn dirs(&self) -> AppDirs {
+        AppDirs {
+            engine_root_dir: self.engine_root_dir.clone(),
+            engine_assets_dir: self.engine_assets_dir.clone(),
+            project_root_dir: self.project_root_dir.clone(),
+            project_assets_dir: self.project_assets_dir.clone(),
+        }
     }
 
     pub fn assets_dir_name() -> &'static str {
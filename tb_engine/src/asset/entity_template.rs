@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use errors::*;
+use tb_core::serde_json::{self, Value};
+use tb_core::*;
+use tb_ecs::*;
+
+use crate::asset::{AssetHandle, AssetLoader};
+
+mod errors {
+    pub use tb_core::error::*;
+
+    error_chain! {}
+}
+
+/// Components of an entity keyed by component name,
+/// e.g. `{"Location": {"location": {"x": 0, "y": 0, "z": 0}}}`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct EntityTemplate {
+    components: Value,
+}
+
+impl EntityTemplate {
+    pub fn new(components: Value) -> Self {
+        Self { components }
+    }
+
+    /// Read a template written by hand, which isn't tagged with its asset type
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .chain_err(|| format!("Failed to open entity template. path: {:?}", path))?;
+        serde_json::from_reader(file)
+            .chain_err(|| format!("Failed to deserialize entity template. path: {:?}", path))
+    }
+
+    pub fn spawn(&self, world: &mut World) -> Result<Entity> {
+        world
+            .spawn_from_json(&self.components)
+            .chain_err(|| "Failed to spawn entity template")
+    }
+}
+
+pub trait WorldEntityTemplate {
+    /// Spawn an entity from a template loaded by the `AssetLoader`.
+    /// Returns `None` if the template isn't loaded yet.
+    fn spawn_template(&mut self, template: AssetHandle<EntityTemplate>) -> Result<Option<Entity>>;
+}
+
+impl WorldEntityTemplate for World {
+    fn spawn_template(&mut self, template: AssetHandle<EntityTemplate>) -> Result<Option<Entity>> {
+        let template = unsafe { self.try_fetch::<AssetLoader>() }
+            .ok()
+            .and_then(|asset_loader| asset_loader.get(template))
+            .cloned();
+        template.map(|template| template.spawn(self)).transpose()
+    }
+}
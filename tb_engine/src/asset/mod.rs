@@ -11,9 +11,10 @@ use std::sync::{Arc, Mutex};
 use errors::*;
 use tb_ecs::*;
 
-use crate::path::TbPath;
+use crate::path::{AppDirs, TbPath};
 
 pub mod entity_instance;
+pub mod entity_template;
 pub mod prefab;
 
 mod errors {
@@ -58,6 +59,7 @@ type CompletedAssetsChannel = (
 );
 
 pub struct AssetLoader {
+    dirs: AppDirs,
     id_to_assets: HashMap<u64, AssetArc>,
     path_to_ids: HashMap<PathBuf, u64>,
     next_id: u64,
@@ -73,9 +75,26 @@ pub struct AssetLoader {
 unsafe impl Sync for AssetLoader {}
 
 impl AssetLoader {
+    /// The `TbPath`s of the assets are resolved in `dirs`
+    pub fn new(dirs: AppDirs) -> Self {
+        Self {
+            dirs,
+            id_to_assets: Default::default(),
+            path_to_ids: Default::default(),
+            next_id: 0,
+            threads: Default::default(),
+            id_to_pending_channel: Default::default(),
+            completed_assets_channel: channel(),
+        }
+    }
+
+    pub fn dirs(&self) -> &AppDirs {
+        &self.dirs
+    }
+
     pub fn load<T: Asset>(&mut self, path: TbPath) -> AssetHandle<T> {
         let id_to_pending_channel = &mut self.id_to_pending_channel;
-        let id = match self.path_to_ids.entry(path.into()) {
+        let id = match self.path_to_ids.entry(path.to_absolute(&self.dirs)) {
             Entry::Occupied(occupied) => *occupied.get(),
             Entry::Vacant(vacant) => {
                 let id = self.next_id;
@@ -101,7 +120,7 @@ impl AssetLoader {
     }
 
     pub fn save<T: Asset>(&mut self, path: TbPath, asset: Box<T>) -> AssetHandle<T> {
-        let id = match self.path_to_ids.entry(path.into()) {
+        let id = match self.path_to_ids.entry(path.to_absolute(&self.dirs)) {
             Entry::Occupied(occupied) => *occupied.get(),
             Entry::Vacant(vacant) => {
                 let id = self.next_id;
@@ -144,6 +163,17 @@ impl AssetLoader {
         }
     }
 
+    /// Write the asset to the file right away, in the format read by `load`,
+    /// e.g. to create the assets of a new project
+    pub fn write_file<T: Asset>(path: impl AsRef<Path>, asset: T) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .chain_err(|| format!("Failed to create asset file. path: {:?}", path))?;
+        let asset = SerdeBox(Box::new(asset) as Box<dyn Asset>);
+        serde_json::to_writer_pretty(file, &asset)
+            .chain_err(|| format!("Failed to serialize asset. path: {:?}", path))
+    }
+
     fn save_block(path: impl AsRef<Path>, asset: AssetArc) -> Result<AssetArc> {
         let path = path.as_ref();
         let file = match Self::open_file(path) {
@@ -226,16 +256,10 @@ impl AssetLoader {
     }
 }
 
+/// Resolves the paths in `AppDirs::default`
 impl Default for AssetLoader {
     fn default() -> Self {
-        Self {
-            id_to_assets: Default::default(),
-            path_to_ids: Default::default(),
-            next_id: 0,
-            threads: Default::default(),
-            id_to_pending_channel: Default::default(),
-            completed_assets_channel: channel(),
-        }
+        Self::new(AppDirs::default())
     }
 }
 
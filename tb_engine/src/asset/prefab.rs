@@ -23,7 +23,7 @@ pub struct PrefabLink {
     link: LocalToWorldLink,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct Prefab {}
 
 impl Prefab {
@@ -36,9 +36,9 @@ impl Prefab {
     //     let entities = RecursiveChildrenIter::new(children_components, names, root);
     //
     //     let extern_folder = dest_file
-    //         .join_prefix_assets_based(AppInfo::extern_entity_dir_name())
+    //         .join_prefix_assets_based(AppInfo::extern_entity_dir_name(), asset_loader.dirs())
     //         .chain_err(|| "Failed to get assets based path")?
-    //         .to_absolute();
+    //         .to_absolute(asset_loader.dirs());
     //
     //     let parents = unsafe { world.fetch_components::<Parent>() };
     //     for (entity, path) in entities {
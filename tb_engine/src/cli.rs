@@ -0,0 +1,369 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tb_core::serde_json;
+
+use errors::*;
+
+mod errors {
+    pub use tb_core::error::*;
+
+    error_chain! {}
+}
+
+const USAGE: &str = "\
+usage: toybox [command] [options]
+
+commands:
+    run                 run the project, the default
+    new <name>          create a project in the dir <name>
+    build               build the plugins of the project
+    pack                build the project and collect what it needs to run
+    check               check the project without building it
+
+options:
+    -p, --project <dir>     the project dir, defaults to the current dir
+    --release               build and load the plugins with the release profile
+    --debug                 build and load the plugins with the debug profile
+    --fps <fps>             the frames per second
+    --headless              run without window and input, as fast as possible on a
+                            simulated clock
+    --frames <n>            stop after <n> frames
+    --log-level <level>     error, warn, info, debug or trace
+    --entry-level <path>    the first level, relative to the project assets
+    -o, --output <dir>      where `pack` collects the project, defaults to target/pack
+    --engine <dir>          the toybox source dir `new` projects depend on, found from the
+                            binary by default
+    -h, --help              print this
+";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliCommand {
+    Run,
+    New {
+        name: String,
+        engine: Option<PathBuf>,
+    },
+    Build,
+    Pack {
+        output: Option<PathBuf>,
+    },
+    Check,
+    Help,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    Debug,
+    Release,
+}
+
+impl Profile {
+    /// The dir of the profile in the cargo target dir
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn parse(level: &str) -> Result<Self> {
+        Ok(match level {
+            "error" => LogLevel::Error,
+            "warn" => LogLevel::Warn,
+            "info" => LogLevel::Info,
+            "debug" => LogLevel::Debug,
+            "trace" => LogLevel::Trace,
+            _ => bail!("unknown log level: {}", level),
+        })
+    }
+}
+
+/// The settings of the app, see `SettingsOverrides`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub profile: Profile,
+    pub fps: f32,
+    /// No window and input, the frames run without sleeping on a simulated clock
+    pub headless: bool,
+    /// Stop after the frames, run until `AppExit` is inserted otherwise
+    pub frames: Option<u64>,
+    pub log_level: LogLevel,
+    /// Relative to the project assets
+    pub entry_level: PathBuf,
+}
+
+impl Settings {
+    /// Whether messages of `level` are printed
+    pub fn log_enabled(&self, level: LogLevel) -> bool {
+        level <= self.log_level
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            // the same as the engine
+            profile: if cfg!(debug_assertions) {
+                Profile::Debug
+            } else {
+                Profile::Release
+            },
+            fps: 30.0,
+            headless: false,
+            frames: None,
+            log_level: LogLevel::Info,
+            entry_level: PathBuf::from("levels/entry.tbasset"),
+        }
+    }
+}
+
+/// The settings given on the command line or in the project config file.
+/// Those given on the command line win, the ones given in neither are the defaults.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettingsOverrides {
+    pub profile: Option<Profile>,
+    pub fps: Option<f32>,
+    pub headless: Option<bool>,
+    pub frames: Option<u64>,
+    pub log_level: Option<LogLevel>,
+    pub entry_level: Option<PathBuf>,
+}
+
+impl SettingsOverrides {
+    /// The project config file in the project root
+    pub fn config_file_name() -> &'static str {
+        "toybox.json"
+    }
+
+    /// Load the config file of the project, empty if there is none
+    pub fn load_config(project_dir: &Path) -> Result<Self> {
+        let path = project_dir.join(Self::config_file_name());
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = File::open(&path).chain_err(|| format!("Failed to open config: {:?}", path))?;
+        serde_json::from_reader(file).chain_err(|| format!("Failed to parse config: {:?}", path))
+    }
+
+    /// The ones not given here are taken from `other`
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            profile: self.profile.or(other.profile),
+            fps: self.fps.or(other.fps),
+            headless: self.headless.or(other.headless),
+            frames: self.frames.or(other.frames),
+            log_level: self.log_level.or(other.log_level),
+            entry_level: self.entry_level.or(other.entry_level),
+        }
+    }
+
+    pub fn into_settings(self) -> Settings {
+        let default = Settings::default();
+        Settings {
+            profile: self.profile.unwrap_or(default.profile),
+            fps: self.fps.unwrap_or(default.fps),
+            headless: self.headless.unwrap_or(default.headless),
+            frames: self.frames.or(default.frames),
+            log_level: self.log_level.unwrap_or(default.log_level),
+            entry_level: self.entry_level.unwrap_or(default.entry_level),
+        }
+    }
+}
+
+/// The parsed command line of the toybox binary
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cli {
+    pub command: CliCommand,
+    pub project_dir: Option<PathBuf>,
+    pub overrides: SettingsOverrides,
+}
+
+impl Default for Cli {
+    fn default() -> Self {
+        Self {
+            command: CliCommand::Run,
+            project_dir: None,
+            overrides: Default::default(),
+        }
+    }
+}
+
+impl Cli {
+    pub fn usage() -> &'static str {
+        USAGE
+    }
+
+    /// Parse the arguments, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = Self::default();
+        let mut command = None;
+        let mut positionals = vec![];
+        let mut output = None;
+        let mut engine = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| Error::from(format!("missing value of {}", name)))
+            };
+            let overrides = &mut cli.overrides;
+            match arg.as_str() {
+                "-p" | "--project" => cli.project_dir = Some(PathBuf::from(value(&arg)?)),
+                "--release" => overrides.profile = Some(Profile::Release),
+                "--debug" => overrides.profile = Some(Profile::Debug),
+                "--fps" => {
+                    let fps = value(&arg)?;
+                    overrides.fps = Some(match fps.parse() {
+                        Ok(fps) if fps > 0.0 => fps,
+                        _ => bail!("invalid fps: {}", fps),
+                    })
+                }
+                "--headless" => overrides.headless = Some(true),
+                "--frames" => {
+                    let frames = value(&arg)?;
+                    overrides.frames = Some(match frames.parse() {
+                        Ok(frames) => frames,
+                        _ => bail!("invalid frames: {}", frames),
+                    })
+                }
+                "--log-level" => overrides.log_level = Some(LogLevel::parse(&value(&arg)?)?),
+                "--entry-level" => overrides.entry_level = Some(PathBuf::from(value(&arg)?)),
+                "-o" | "--output" => output = Some(PathBuf::from(value(&arg)?)),
+                "--engine" => engine = Some(PathBuf::from(value(&arg)?)),
+                "-h" | "--help" => command = Some("help".to_owned()),
+                flag if flag.starts_with('-') => bail!("unknown option: {}", flag),
+                _ if command.is_none() => command = Some(arg),
+                _ => positionals.push(arg),
+            }
+        }
+
+        let mut positionals = positionals.into_iter();
+        cli.command = match command.as_deref().unwrap_or("run") {
+            "run" => CliCommand::Run,
+            "new" => CliCommand::New {
+                name: positionals
+                    .next()
+                    .ok_or("missing name of the new project")?,
+                engine: engine.take(),
+            },
+            "build" => CliCommand::Build,
+            "pack" => CliCommand::Pack {
+                output: output.take(),
+            },
+            "check" => CliCommand::Check,
+            "help" => CliCommand::Help,
+            command => bail!("unknown command: {}", command),
+        };
+        if let Some(positional) = positionals.next() {
+            bail!("unexpected argument: {}", positional);
+        }
+        if output.is_some() {
+            bail!("--output is only for pack");
+        }
+        if engine.is_some() {
+            bail!("--engine is only for new");
+        }
+        Ok(cli)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse(&[]).unwrap(), Cli::default());
+        let cli = parse(&["-p", "game", "--release", "--fps", "60"]).unwrap();
+        assert_eq!(cli.command, CliCommand::Run);
+        assert_eq!(cli.project_dir, Some(PathBuf::from("game")));
+        assert_eq!(cli.overrides.profile, Some(Profile::Release));
+        assert_eq!(cli.overrides.fps, Some(60.0));
+
+        let cli = parse(&["new", "game", "--log-level", "debug"]).unwrap();
+        assert_eq!(
+            cli.command,
+            CliCommand::New {
+                name: "game".to_owned(),
+                engine: None
+            }
+        );
+        assert_eq!(
+            parse(&["new", "game", "--engine", "toybox"])
+                .unwrap()
+                .command,
+            CliCommand::New {
+                name: "game".to_owned(),
+                engine: Some(PathBuf::from("toybox"))
+            }
+        );
+        assert_eq!(cli.overrides.log_level, Some(LogLevel::Debug));
+        let cli = parse(&["pack", "-o", "out", "--headless", "--frames", "600"]).unwrap();
+        assert_eq!(
+            cli.command,
+            CliCommand::Pack {
+                output: Some(PathBuf::from("out"))
+            }
+        );
+        assert_eq!(cli.overrides.headless, Some(true));
+        assert_eq!(cli.overrides.frames, Some(600));
+        assert_eq!(
+            parse(&["check", "--help"]).unwrap().command,
+            CliCommand::Help
+        );
+
+        assert!(parse(&["new"]).is_err());
+        assert!(parse(&["build", "extra"]).is_err());
+        assert!(parse(&["launch"]).is_err());
+        assert!(parse(&["--fps", "fast"]).is_err());
+        assert!(parse(&["--frames", "-1"]).is_err());
+        assert!(parse(&["--log-level"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+        assert!(parse(&["run", "-o", "out"]).is_err());
+        assert!(parse(&["build", "--engine", "toybox"]).is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let config: SettingsOverrides = serde_json::from_value(serde_json::json!({
+            "profile": "release",
+            "fps": 20.0,
+            "frames": 100,
+            "entry_level": "levels/menu.tbasset"
+        }))
+        .unwrap();
+        let cli = parse(&["--debug", "--headless"]).unwrap();
+        let settings = cli.overrides.or(config).into_settings();
+        assert_eq!(settings.profile, Profile::Debug);
+        assert_eq!(settings.fps, 20.0);
+        assert!(settings.headless);
+        assert_eq!(settings.frames, Some(100));
+        assert_eq!(settings.log_level, LogLevel::Info);
+        assert_eq!(settings.entry_level, PathBuf::from("levels/menu.tbasset"));
+
+        let unknown = serde_json::from_value::<SettingsOverrides>(serde_json::json!({
+            "vsync": true
+        }));
+        assert!(unknown.is_err());
+    }
+}
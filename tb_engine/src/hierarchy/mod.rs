@@ -3,7 +3,7 @@ use std::path::PathBuf;
 
 use tb_ecs::*;
 
-#[component]
+#[component(index = "name")]
 pub struct Name {
     pub name: String,
 }
@@ -18,6 +18,88 @@ pub struct Children {
     children: Vec<Entity>,
 }
 
+/// Hierarchy operations on `World`.
+/// They are a trait here rather than inherent methods of `World`, because `World` is defined in
+/// `tb_ecs`, which does not know `Parent` and `Children`.
+pub trait WorldHierarchy {
+    /// Duplicate `root` and all its descendants with `World::clone_entities`.
+    /// The copy of `root` is added to the children of its parent.
+    fn clone_subtree(&mut self, root: Entity) -> Entity;
+
+    /// Enable or disable `root` and all its descendants.
+    fn set_subtree_enabled(&mut self, root: Entity, enabled: bool);
+
+    /// Whether the entity and all its ancestors are enabled.
+    fn is_enabled_in_hierarchy(&self, entity: Entity) -> bool;
+}
+
+impl WorldHierarchy for World {
+    fn clone_subtree(&mut self, root: Entity) -> Entity {
+        let subtree = subtree(self, root);
+        let clone = self.clone_entities(&subtree)[0];
+        unsafe {
+            let parent = self
+                .try_fetch::<ComponentStorage<Parent>>()
+                .ok()
+                .and_then(|parents| parents.fetch(clone));
+            if let Some(parent) = parent {
+                if let Ok(children_components) =
+                    self.try_fetch_mut::<ComponentStorage<Children>>()
+                {
+                    if let Some(children) = children_components.fetch_mut(parent.entity) {
+                        children.children.push(clone);
+                    }
+                }
+            }
+        }
+        clone
+    }
+
+    fn set_subtree_enabled(&mut self, root: Entity, enabled: bool) {
+        for entity in subtree(self, root) {
+            if enabled {
+                self.enable(entity);
+            } else {
+                self.disable(entity);
+            }
+        }
+    }
+
+    fn is_enabled_in_hierarchy(&self, entity: Entity) -> bool {
+        let parents = unsafe { self.try_fetch::<ComponentStorage<Parent>>() };
+        let mut entity = Some(entity);
+        while let Some(e) = entity {
+            if !self.is_enabled(e) {
+                return false;
+            }
+            entity = parents
+                .as_ref()
+                .ok()
+                .and_then(|parents| parents.fetch(e))
+                .map(|parent| parent.entity);
+        }
+        true
+    }
+}
+
+/// `root` and its descendants in depth-first order.
+fn subtree(world: &World, root: Entity) -> Vec<Entity> {
+    let mut subtree = vec![];
+    let children_components = unsafe { world.try_fetch::<ComponentStorage<Children>>() };
+    let mut stack = vec![root];
+    while let Some(entity) = stack.pop() {
+        subtree.push(entity);
+        if let Some(children) = children_components
+            .as_ref()
+            .ok()
+            .and_then(|children_components| children_components.fetch(entity))
+        {
+            stack.extend(children.children.iter().rev());
+        }
+    }
+    subtree
+}
+
 pub struct RecursiveChildrenIter<'s> {
     children_components: &'s ComponentStorage<Children>,
     names: &'s ComponentStorage<Name>,
@@ -98,7 +180,7 @@ mod tests {
 
     use tb_ecs::*;
 
-    use crate::hierarchy::{Children, Name, RecursiveChildrenIter};
+    use crate::hierarchy::{Children, Name, Parent, RecursiveChildrenIter, WorldHierarchy};
 
     #[test]
     fn recursive_children_iter() {
@@ -158,4 +240,70 @@ mod tests {
                 .collect::<Vec<Entity>>()
         )
     }
+
+    /// A root with a child and a grandchild, returned in this order
+    fn create_family(world: &mut World) -> (Entity, Entity, Entity) {
+        let root = world.create_entity().create();
+        let child = world.create_entity().with(Parent { entity: root }).create();
+        let grandchild = world.create_entity().with(Parent { entity: child }).create();
+        world.insert_components::<Children>();
+        let mut children_components = unsafe { WriteComps::<Children>::fetch(world) };
+        children_components.insert(
+            root,
+            Children {
+                children: vec![child],
+            },
+        );
+        children_components.insert(
+            child,
+            Children {
+                children: vec![grandchild],
+            },
+        );
+        (root, child, grandchild)
+    }
+
+    #[test]
+    fn clone_subtree() {
+        let mut world = World::default();
+        let (root, child, grandchild) = create_family(&mut world);
+
+        let cloned_child = world.clone_subtree(child);
+
+        let children_components = unsafe { world.fetch_components::<Children>() };
+        let parents = unsafe { world.fetch_components::<Parent>() };
+        assert_eq!(
+            children_components.fetch(root).unwrap().children,
+            vec![child, cloned_child]
+        );
+        assert_eq!(parents.fetch(cloned_child).unwrap().entity, root);
+        let cloned_grandchild = children_components.fetch(cloned_child).unwrap().children[0];
+        assert_ne!(cloned_grandchild, grandchild);
+        assert_eq!(
+            parents.fetch(cloned_grandchild).unwrap().entity,
+            cloned_child
+        );
+    }
+
+    #[test]
+    fn set_subtree_enabled() {
+        let mut world = World::default();
+        let (root, child, grandchild) = create_family(&mut world);
+
+        world.disable(root);
+        assert!(world.is_enabled(grandchild));
+        assert!(!world.is_enabled_in_hierarchy(grandchild));
+
+        world.set_subtree_enabled(child, false);
+        assert!(!world.is_enabled(child));
+        assert!(!world.is_enabled(grandchild));
+        let parents = unsafe { RBWComps::<Parent>::fetch(&world) };
+        assert_eq!((&parents).join().count(), 0);
+        assert_eq!((&parents).include_disabled().join().count(), 2);
+
+        world.set_subtree_enabled(root, true);
+        assert!(world.is_enabled_in_hierarchy(grandchild));
+        let parents = unsafe { RBWComps::<Parent>::fetch(&world) };
+        assert_eq!((&parents).join().count(), 2);
+    }
 }
@@ -13,7 +13,7 @@ mod errors {
     error_chain! {}
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct Level {
     root: Prefab,
 }
@@ -1,10 +1,13 @@
 #![feature(once_cell)]
 
+pub use time::*;
 pub use transform::*;
 
 pub mod app_info;
 pub mod asset;
+pub mod cli;
 pub mod hierarchy;
 pub mod level;
 pub mod path;
+pub mod time;
 pub mod transform;
@@ -13,6 +13,48 @@ enum TbPathBase {
     ProjectAssets,
 }
 
+/// The dirs `TbPath`s are relative to, see `AppInfo::dirs`
+#[derive(Clone, Debug)]
+pub struct AppDirs {
+    pub engine_root_dir: PathBuf,
+    pub engine_assets_dir: PathBuf,
+    pub project_root_dir: PathBuf,
+    pub project_assets_dir: PathBuf,
+}
+
+impl AppDirs {
+    /// The assets dirs are the ones in the root dirs
+    pub fn new(engine_root_dir: PathBuf, project_root_dir: PathBuf) -> Self {
+        Self {
+            engine_assets_dir: engine_root_dir.join(AppInfo::assets_dir_name()),
+            engine_root_dir,
+            project_assets_dir: project_root_dir.join(AppInfo::assets_dir_name()),
+            project_root_dir,
+        }
+    }
+
+    fn base_dir(&self, base: TbPathBase) -> Option<&PathBuf> {
+        match base {
+            TbPathBase::Absolute => None,
+            TbPathBase::EngineRoot => Some(&self.engine_root_dir),
+            TbPathBase::EngineAssets => Some(&self.engine_assets_dir),
+            TbPathBase::ProjectRoot => Some(&self.project_root_dir),
+            TbPathBase::ProjectAssets => Some(&self.project_assets_dir),
+        }
+    }
+}
+
+/// The engine next to the executable and the project in the current dir,
+/// as for an app started without a project
+impl Default for AppDirs {
+    fn default() -> Self {
+        Self::new(
+            path_util::exe_dir(),
+            std::env::current_dir().unwrap_or_default(),
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct TbPath {
     base: TbPathBase,
@@ -26,19 +68,19 @@ impl TbPath {
             path: path.into(),
         }
     }
-    pub fn to_absolute(&self) -> PathBuf {
-        let base = match self.base {
-            TbPathBase::Absolute => return self.path.clone(),
-            TbPathBase::EngineRoot => &AppInfo::get().engine_root_dir,
-            TbPathBase::EngineAssets => &AppInfo::get().engine_assets_dir,
-            TbPathBase::ProjectRoot => &AppInfo::get().project_root_dir,
-            TbPathBase::ProjectAssets => &AppInfo::get().project_assets_dir,
-        };
-        base.join(&self.path)
+    pub fn to_absolute(&self, dirs: &AppDirs) -> PathBuf {
+        match dirs.base_dir(self.base) {
+            Some(base) => base.join(&self.path),
+            None => self.path.clone(),
+        }
     }
 
-    pub fn join_prefix_assets_based(&self, prefix: impl Into<PathBuf>) -> Option<TbPath> {
-        self.to_assets_based().map(|mut assets_based| {
+    pub fn join_prefix_assets_based(
+        &self,
+        prefix: impl Into<PathBuf>,
+        dirs: &AppDirs,
+    ) -> Option<TbPath> {
+        self.to_assets_based(dirs).map(|mut assets_based| {
             let mut prefix = prefix.into();
             std::mem::swap(&mut prefix, &mut assets_based.path);
             assets_based.path.push(prefix);
@@ -46,12 +88,11 @@ impl TbPath {
         })
     }
 
-    fn to_assets_based(&self) -> Option<TbPath> {
+    fn to_assets_based(&self, dirs: &AppDirs) -> Option<TbPath> {
         match self.base {
             TbPathBase::Absolute => {
-                let app_info = AppInfo::get();
-                match path_util::pre_cut(&self.path, &app_info.project_assets_dir) {
-                    None => path_util::pre_cut(&self.path, &app_info.engine_assets_dir).map(
+                match path_util::pre_cut(&self.path, &dirs.project_assets_dir) {
+                    None => path_util::pre_cut(&self.path, &dirs.engine_assets_dir).map(
                         |engine_assets_based_path| TbPath {
                             base: TbPathBase::EngineAssets,
                             path: engine_assets_based_path,
@@ -79,15 +120,24 @@ impl TbPath {
     }
 }
 
-impl From<TbPath> for PathBuf {
-    fn from(from: TbPath) -> Self {
-        let base = match from.base {
-            TbPathBase::Absolute => return from.path,
-            TbPathBase::EngineRoot => &AppInfo::get().engine_root_dir,
-            TbPathBase::EngineAssets => &AppInfo::get().engine_assets_dir,
-            TbPathBase::ProjectRoot => &AppInfo::get().project_root_dir,
-            TbPathBase::ProjectAssets => &AppInfo::get().project_assets_dir,
-        };
-        base.join(from.path)
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn resolve_in_dirs() {
+        let dirs = AppDirs::new(PathBuf::from("/engine"), PathBuf::from("/project"));
+        let path = TbPath::new_project_assets("levels/entry.tbasset");
+        assert_eq!(
+            path.to_absolute(&dirs),
+            PathBuf::from("/project/assets/levels/entry.tbasset")
+        );
+        let prefixed = path.join_prefix_assets_based("extern", &dirs).unwrap();
+        assert_eq!(
+            prefixed.to_absolute(&dirs),
+            PathBuf::from("/project/assets/extern/levels/entry.tbasset")
+        );
     }
 }
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+/// The clock of the app, advanced before the systems run in each frame
+#[derive(Default, Debug)]
+pub struct Time {
+    frame: u64,
+    delta: Duration,
+    elapsed: Duration,
+}
+
+impl Time {
+    /// The frames run, the current one included
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// The time since the last frame
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// The time since the first frame, the current one included
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Start the next frame, `delta` after the current one
+    pub fn advance(&mut self, delta: Duration) {
+        self.frame += 1;
+        self.delta = delta;
+        self.elapsed += delta;
+    }
+}
+
+/// Inserted to stop the app after the current frame
+#[derive(Default, Debug)]
+pub struct AppExit;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance() {
+        let mut time = Time::default();
+        time.advance(Duration::from_millis(20));
+        time.advance(Duration::from_millis(30));
+        assert_eq!(time.frame(), 2);
+        assert_eq!(time.delta(), Duration::from_millis(30));
+        assert_eq!(time.elapsed(), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,12 @@
+use std::process::Command;
+
+/// The rustc version is part of the plugin abi, the rust abi is not stable across compilers
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let output = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .expect("Failed to run rustc --version");
+    let version = String::from_utf8(output.stdout).unwrap();
+    println!("cargo:rustc-env=TB_RUSTC_VERSION={}", version.trim());
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use tb_core::algorithm::topological_sort::TopologicalGraph;
+
+use crate::errors::*;
+
+/// Bumped when the layout of `PluginAbi` or the symbols a plugin exports change
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const RUSTC_VERSION: &str = env!("TB_RUSTC_VERSION");
+
+#[repr(C)]
+pub struct AbiStr {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl AbiStr {
+    const fn new(s: &'static str) -> Self {
+        Self {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        unsafe { std::str::from_utf8_unchecked(std::slice::from_raw_parts(self.ptr, self.len)) }
+    }
+}
+
+/// What a plugin is built against, exported as `_plugin_abi`.
+/// It is read before anything else of the library, so its layout must never change.
+#[repr(C)]
+pub struct PluginAbi {
+    abi_version: u32,
+    engine_version: AbiStr,
+    rustc_version: AbiStr,
+}
+
+unsafe impl Sync for PluginAbi {}
+
+impl PluginAbi {
+    pub const CURRENT: PluginAbi = PluginAbi {
+        abi_version: PLUGIN_ABI_VERSION,
+        engine_version: AbiStr::new(ENGINE_VERSION),
+        rustc_version: AbiStr::new(RUSTC_VERSION),
+    };
+
+    /// Whether a plugin built against `self` can be loaded by the running engine
+    pub fn check(&self) -> Result<()> {
+        let incompatible = ErrorKind::IncompatiblePlugin;
+        if self.abi_version != PLUGIN_ABI_VERSION {
+            bail!(incompatible(format!(
+                "plugin abi version {}, expected {}",
+                self.abi_version, PLUGIN_ABI_VERSION
+            )));
+        }
+        if self.rustc_version.as_str() != RUSTC_VERSION {
+            bail!(incompatible(format!(
+                "built by {}, expected {}",
+                self.rustc_version.as_str(),
+                RUSTC_VERSION
+            )));
+        }
+        if !is_version_compatible(self.engine_version.as_str(), ENGINE_VERSION) {
+            bail!(incompatible(format!(
+                "requires engine {}, running {}",
+                self.engine_version.as_str(),
+                ENGINE_VERSION
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Exported by `declare_plugin!` as `_plugin_descriptor`, read once the abi is checked
+#[derive(Clone, Debug)]
+pub struct PluginDescriptor {
+    /// The library name, which dependencies refer to
+    pub name: String,
+    pub version: String,
+    /// Loaded before the plugin, which fails to load without them
+    pub dependencies: Vec<String>,
+    /// Loaded before the plugin if they are loaded at all
+    pub optional_dependencies: Vec<String>,
+}
+
+/// Semver compatibility: `current` is not older than `required` and has the same major version,
+/// or the same minor version for 0.x.
+fn is_version_compatible(required: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Option<(u64, u64, u64)> {
+        let mut parts = version.split(|c| c == '.' || c == '-' || c == '+');
+        Some((
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+        ))
+    };
+    match (parse(required), parse(current)) {
+        (Some(required), Some(current)) => {
+            let same_series = match required {
+                (0, 0, _) => required == current,
+                (0, minor, _) => current.0 == 0 && current.1 == minor,
+                (major, _, _) => current.0 == major,
+            };
+            same_series && current >= required
+        }
+        _ => required == current,
+    }
+}
+
+/// The file name of the library on this platform, e.g. `libfoo.so`
+pub(crate) fn library_file_name(lib_name: &str) -> String {
+    format!(
+        "{}{}{}",
+        std::env::consts::DLL_PREFIX,
+        lib_name,
+        std::env::consts::DLL_SUFFIX
+    )
+}
+
+/// The library name of a library file on this platform, the reverse of `library_file_name`
+pub(crate) fn library_name(file_name: &str) -> Option<&str> {
+    file_name
+        .strip_prefix(std::env::consts::DLL_PREFIX)?
+        .strip_suffix(std::env::consts::DLL_SUFFIX)
+}
+
+pub(crate) fn find_library(lib_name: &str, search_dirs: &[PathBuf]) -> Result<PathBuf> {
+    let file_name = library_file_name(lib_name);
+    search_dirs
+        .iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.exists())
+        .ok_or_else(|| ErrorKind::PluginNotFound(lib_name.to_owned(), search_dirs.to_vec()).into())
+}
+
+/// Check the abi of the library and read its descriptor, without creating the plugin.
+/// The opened library is returned to be dropped once the plugin is loaded: while it is open,
+/// opening the same file again reuses the mapping instead of running its static registrations
+/// again. It is not kept open after that.
+pub(crate) fn read_descriptor(
+    lib_name: &str,
+    path: &Path,
+) -> Result<(PluginDescriptor, libloading::Library)> {
+    type PluginDescriptorFn = fn() -> PluginDescriptor;
+    unsafe {
+        let lib = libloading::Library::new(path)
+            .chain_err(|| format!("Failed to open plugin library: {:?}", path))?;
+        let descriptor = {
+            let abi: libloading::Symbol<*const PluginAbi> = lib
+                .get(b"_plugin_abi")
+                .chain_err(|| ErrorKind::NotAPlugin(path.to_owned()))?;
+            (**abi).check()?;
+            let descriptor: libloading::Symbol<PluginDescriptorFn> = lib
+                .get(b"_plugin_descriptor")
+                .chain_err(|| "Failed to find _plugin_descriptor symbol")?;
+            descriptor()
+        };
+        if descriptor.name != lib_name {
+            bail!(ErrorKind::IncompatiblePlugin(format!(
+                "declared as {}",
+                descriptor.name
+            )));
+        }
+        Ok((descriptor, lib))
+    }
+}
+
+/// The order to load the plugins in, dependencies first.
+/// `loaded` plugins are not loaded again, but can be depended on.
+pub(crate) fn resolve_load_order(
+    descriptors: &[PluginDescriptor],
+    loaded: &HashSet<String>,
+) -> Result<Vec<PluginDescriptor>> {
+    let by_name: HashMap<&str, &PluginDescriptor> = descriptors
+        .iter()
+        .map(|descriptor| (descriptor.name.as_str(), descriptor))
+        .collect();
+    let mut graph = TopologicalGraph::default();
+    for descriptor in descriptors {
+        graph.add_item(descriptor.name.as_str());
+        for dependency in &descriptor.dependencies {
+            if by_name.contains_key(dependency.as_str()) {
+                graph.add_dependency(descriptor.name.as_str(), dependency.as_str());
+            } else if !loaded.contains(dependency) {
+                bail!(ErrorKind::MissingDependency(
+                    descriptor.name.clone(),
+                    dependency.clone()
+                ));
+            }
+        }
+        for dependency in &descriptor.optional_dependencies {
+            if by_name.contains_key(dependency.as_str()) {
+                graph.add_dependency(descriptor.name.as_str(), dependency.as_str());
+            }
+        }
+    }
+    let mut order = Vec::with_capacity(descriptors.len());
+    for name in graph.iter() {
+        let name = name.chain_err(|| ErrorKind::DependencyCycle)?;
+        order.push(by_name[name].clone());
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn descriptor(name: &str, dependencies: &[&str], optional: &[&str]) -> PluginDescriptor {
+        PluginDescriptor {
+            name: name.to_owned(),
+            version: "0.1.0".to_owned(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            optional_dependencies: optional.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    fn names(order: Vec<PluginDescriptor>) -> Vec<String> {
+        order
+            .into_iter()
+            .map(|descriptor| descriptor.name)
+            .collect()
+    }
+
+    #[test]
+    fn version_compatibility() {
+        assert!(is_version_compatible("0.1.0", "0.1.3"));
+        assert!(!is_version_compatible("0.1.3", "0.1.0"));
+        assert!(!is_version_compatible("0.1.0", "0.2.0"));
+        assert!(is_version_compatible("1.2.0", "1.4.1"));
+        assert!(!is_version_compatible("1.2.0", "2.0.0"));
+        assert!(!is_version_compatible("0.0.1", "0.0.2"));
+    }
+
+    #[test]
+    fn load_order() {
+        let loaded: HashSet<String> = vec!["core".to_owned()].into_iter().collect();
+        let order = resolve_load_order(
+            &[
+                descriptor("game", &["physics", "core"], &["audio", "editor"]),
+                descriptor("audio", &[], &[]),
+                descriptor("physics", &[], &[]),
+            ],
+            &loaded,
+        )
+        .unwrap();
+        let order = names(order);
+        assert_eq!(order.len(), 3);
+        assert_eq!(order[2], "game");
+
+        let missing = resolve_load_order(&[descriptor("game", &["physics"], &[])], &loaded);
+        assert!(matches!(
+            missing.unwrap_err().kind(),
+            ErrorKind::MissingDependency(plugin, dependency) if plugin == "game" && dependency == "physics"
+        ));
+
+        let cycle = resolve_load_order(
+            &[descriptor("a", &["b"], &[]), descriptor("b", &[], &["a"])],
+            &loaded,
+        );
+        assert!(matches!(
+            cycle.unwrap_err().kind(),
+            ErrorKind::DependencyCycle
+        ));
+    }
+}
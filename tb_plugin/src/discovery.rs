@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tb_core::serde_json::{self, Value};
+
+use crate::descriptor::{self, PluginDescriptor};
+use crate::errors::*;
+use crate::PluginManager;
+
+/// The plugins of a project, read from `plugins.json` in the project root
+#[derive(Default, Serialize, Deserialize)]
+pub struct PluginManifest {
+    /// Scanned before the search dirs of the `PluginManager`, relative to the manifest
+    #[serde(default)]
+    pub search_dirs: Vec<PathBuf>,
+    /// By library name, the plugins found but not listed are skipped
+    #[serde(default)]
+    pub plugins: BTreeMap<String, PluginEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PluginEntry {
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Read by the plugin from `PluginConfigs`
+    #[serde(default)]
+    pub config: Value,
+    /// Run the plugin in a child process, see `PluginManager::add_remote_plugin`
+    #[serde(default)]
+    pub remote: Option<RemoteEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RemoteEntry {
+    /// The names of the component types exchanged with the plugin
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl PluginManifest {
+    pub fn file_name() -> &'static str {
+        "plugins.json"
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).chain_err(|| format!("Failed to open manifest: {:?}", path))?;
+        let mut manifest: Self = serde_json::from_reader(file)
+            .chain_err(|| format!("Failed to parse manifest: {:?}", path))?;
+        if let Some(dir) = path.parent() {
+            for search_dir in &mut manifest.search_dirs {
+                *search_dir = dir.join(&search_dir);
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Whether the plugin is enabled and loaded into this process
+    pub(crate) fn is_local(&self, lib_name: &str) -> bool {
+        self.plugins
+            .get(lib_name)
+            .map_or(false, |entry| entry.enabled && entry.remote.is_none())
+    }
+}
+
+/// The configs of the plugins in the manifest by library name, a resource of the world
+#[derive(Default)]
+pub struct PluginConfigs {
+    configs: HashMap<String, Value>,
+}
+
+impl PluginConfigs {
+    pub fn get(&self, lib_name: &str) -> Option<&Value> {
+        self.configs.get(lib_name)
+    }
+}
+
+/// What `PluginManager::discover` did with each plugin
+#[derive(Default, Debug)]
+pub struct DiscoveryReport {
+    pub found: Vec<PluginDescriptor>,
+    pub loaded: Vec<String>,
+    /// The names with the reasons
+    pub skipped: Vec<(String, String)>,
+    /// The names with the errors
+    pub failed: Vec<(String, String)>,
+}
+
+impl Display for DiscoveryReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let found: Vec<_> = self.found.iter().map(|d| d.name.as_str()).collect();
+        writeln!(f, "found plugins: {:?}", found)?;
+        writeln!(f, "loaded plugins: {:?}", self.loaded)?;
+        for (name, reason) in &self.skipped {
+            writeln!(f, "skipped plugin {}: {}", name, reason)?;
+        }
+        for (name, error) in &self.failed {
+            writeln!(f, "failed plugin {}: {}", name, error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The libraries in `dir` by library name
+fn scan_dir(dir: &Path) -> Vec<(String, PathBuf)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            return vec![];
+        }
+    };
+    let mut libs: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let lib_name = descriptor::library_name(&file_name)?;
+            Some((lib_name.to_owned(), entry.path()))
+        })
+        .collect();
+    libs.sort();
+    libs
+}
+
+/// Choose the found plugins to load. Returns the chosen ones, the others are reported.
+fn select(
+    found: &[PluginDescriptor],
+    manifest: Option<&PluginManifest>,
+    loaded: &HashSet<String>,
+    report: &mut DiscoveryReport,
+) -> Vec<PluginDescriptor> {
+    let mut selected = vec![];
+    for descriptor in found {
+        let skipped = if loaded.contains(&descriptor.name) {
+            Some("already loaded")
+        } else {
+            match manifest.map(|manifest| manifest.plugins.get(&descriptor.name)) {
+                Some(None) => Some("not listed in the manifest"),
+                Some(Some(entry)) if !entry.enabled => Some("disabled in the manifest"),
+                _ => None,
+            }
+        };
+        match skipped {
+            Some(reason) => report
+                .skipped
+                .push((descriptor.name.clone(), reason.to_owned())),
+            None => selected.push(descriptor.clone()),
+        }
+    }
+    if let Some(manifest) = manifest {
+        for (name, entry) in &manifest.plugins {
+            if entry.enabled
+                && entry.remote.is_none()
+                && !found.iter().any(|descriptor| &descriptor.name == name)
+            {
+                report
+                    .failed
+                    .push((name.clone(), "not found in the search dirs".to_owned()));
+            }
+        }
+    }
+
+    // drop the plugins missing dependencies, and then the ones depending on them
+    loop {
+        let names: HashSet<String> = selected.iter().map(|d| d.name.clone()).collect();
+        let (kept, dropped): (Vec<_>, Vec<_>) = selected.into_iter().partition(|descriptor| {
+            descriptor
+                .dependencies
+                .iter()
+                .all(|dependency| names.contains(dependency) || loaded.contains(dependency))
+        });
+        selected = kept;
+        if dropped.is_empty() {
+            return selected;
+        }
+        for descriptor in dropped {
+            let missing: Vec<_> = descriptor
+                .dependencies
+                .iter()
+                .filter(|dependency| !names.contains(*dependency) && !loaded.contains(*dependency))
+                .collect();
+            report.failed.push((
+                descriptor.name.clone(),
+                format!("missing dependencies: {:?}", missing),
+            ));
+        }
+    }
+}
+
+impl PluginManager {
+    /// Load the plugins found in the search dirs, or only the ones enabled in `manifest`.
+    /// Only the dirs given to the `PluginManager` and the ones of `manifest` are scanned.
+    /// Libraries without plugin metadata are ignored.
+    /// The configs of the manifest are inserted into the world as `PluginConfigs` by `update`.
+    pub fn discover(&mut self, manifest: Option<&PluginManifest>) -> DiscoveryReport {
+        let mut report = DiscoveryReport::default();
+        if let Some(manifest) = manifest {
+            for dir in manifest.search_dirs.iter().rev() {
+                self.add_search_dir(dir.clone());
+            }
+            self.pending_configs = Some(PluginConfigs {
+                configs: manifest
+                    .plugins
+                    .iter()
+                    .map(|(name, entry)| (name.clone(), entry.config.clone()))
+                    .collect(),
+            });
+        }
+
+        let mut scanned = HashSet::new();
+        let mut inspected_libs = vec![];
+        for dir in self.search_dirs.clone() {
+            for (lib_name, path) in scan_dir(&dir) {
+                if !scanned.insert(lib_name.clone()) {
+                    continue;
+                }
+                // never loaded into this process, nor inspected
+                let remote = manifest
+                    .and_then(|manifest| manifest.plugins.get(&lib_name))
+                    .filter(|entry| entry.enabled)
+                    .and_then(|entry| entry.remote.as_ref());
+                if let Some(remote) = remote {
+                    self.discover_remote(&lib_name, remote, &mut report);
+                    continue;
+                }
+                match descriptor::read_descriptor(&lib_name, &path) {
+                    Ok((descriptor, lib)) => {
+                        report.found.push(descriptor);
+                        inspected_libs.push(lib);
+                    }
+                    Err(e) if matches!(e.kind(), ErrorKind::NotAPlugin(_)) => {}
+                    Err(e) => report
+                        .failed
+                        .push((lib_name, e.display_chain().to_string())),
+                }
+            }
+        }
+
+        if let Some(manifest) = manifest {
+            for (name, entry) in &manifest.plugins {
+                if entry.enabled && entry.remote.is_some() && !scanned.contains(name) {
+                    report
+                        .failed
+                        .push((name.clone(), "not found in the search dirs".to_owned()));
+                }
+            }
+        }
+
+        let loaded = self.descriptors.keys().cloned().collect();
+        let selected = select(&report.found, manifest, &loaded, &mut report);
+        let order = match descriptor::resolve_load_order(&selected, &loaded) {
+            Ok(order) => order,
+            Err(e) => {
+                for descriptor in selected {
+                    report.failed.push((descriptor.name, e.to_string()));
+                }
+                return report;
+            }
+        };
+        let mut failed = HashSet::new();
+        for descriptor in order {
+            let failed_dependencies: Vec<_> = descriptor
+                .dependencies
+                .iter()
+                .filter(|dependency| failed.contains(*dependency))
+                .collect();
+            let result = if failed_dependencies.is_empty() {
+                self.load(descriptor.clone())
+            } else {
+                Err(format!("dependencies failed: {:?}", failed_dependencies).into())
+            };
+            match result {
+                Ok(()) => report.loaded.push(descriptor.name),
+                Err(e) => {
+                    failed.insert(descriptor.name.clone());
+                    report
+                        .failed
+                        .push((descriptor.name, e.display_chain().to_string()));
+                }
+            }
+        }
+        report
+    }
+
+    #[cfg(unix)]
+    fn discover_remote(
+        &mut self,
+        lib_name: &str,
+        remote: &RemoteEntry,
+        report: &mut DiscoveryReport,
+    ) {
+        if self.remote_status(lib_name).is_some() {
+            report
+                .skipped
+                .push((lib_name.to_owned(), "already loaded".to_owned()));
+            return;
+        }
+        match self.add_remote_plugin(lib_name, remote.components.clone(), vec![]) {
+            Ok(()) => report.loaded.push(lib_name.to_owned()),
+            Err(e) => report
+                .failed
+                .push((lib_name.to_owned(), e.display_chain().to_string())),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn discover_remote(
+        &mut self,
+        lib_name: &str,
+        _remote: &RemoteEntry,
+        report: &mut DiscoveryReport,
+    ) {
+        report.failed.push((
+            lib_name.to_owned(),
+            "remote plugins are only supported on unix".to_owned(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tb_core::serde_json::{self, json};
+
+    use super::*;
+
+    fn descriptor(name: &str, dependencies: &[&str]) -> PluginDescriptor {
+        PluginDescriptor {
+            name: name.to_owned(),
+            version: "0.1.0".to_owned(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            optional_dependencies: vec![],
+        }
+    }
+
+    fn names(descriptors: &[PluginDescriptor]) -> Vec<&str> {
+        descriptors.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn select_by_manifest() {
+        let manifest: PluginManifest = serde_json::from_value(json!({
+            "plugins": {
+                "game": { "config": { "level": "entry" } },
+                "physics": { "enabled": false },
+                "audio": {},
+                "missing": {}
+            }
+        }))
+        .unwrap();
+        let found = [
+            descriptor("game", &["physics"]),
+            descriptor("physics", &[]),
+            descriptor("audio", &[]),
+            descriptor("editor", &[]),
+            descriptor("core", &[]),
+        ];
+        let loaded: HashSet<String> = vec!["core".to_owned()].into_iter().collect();
+        let mut report = DiscoveryReport::default();
+        let selected = select(&found, Some(&manifest), &loaded, &mut report);
+        assert_eq!(names(&selected), vec!["audio"]);
+        let skipped: Vec<_> = report
+            .skipped
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(skipped, vec!["physics", "editor", "core"]);
+        let failed: Vec<_> = report
+            .failed
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(failed, vec!["missing", "game"]);
+        assert_eq!(manifest.plugins["game"].config["level"], "entry");
+
+        let mut report = DiscoveryReport::default();
+        let selected = select(&found, None, &loaded, &mut report);
+        assert_eq!(names(&selected), vec!["game", "physics", "audio", "editor"]);
+    }
+
+    #[test]
+    fn scan_libraries() {
+        let dir = std::env::temp_dir().join(format!("tb_plugin_scan_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = descriptor::library_file_name("scanned");
+        std::fs::write(dir.join(&lib), b"").unwrap();
+        std::fs::write(dir.join("scanned.txt"), b"").unwrap();
+        let libs = scan_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(libs, vec![("scanned".to_owned(), dir.join(lib))]);
+    }
+}
@@ -1,32 +1,108 @@
 use std::any::Any;
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::ffi::OsString;
+use std::mem::ManuallyDrop;
+use std::path::{Path, PathBuf};
 
 use live_lib::{LibPartner, Library, Loader, Symbol};
 
+pub use descriptor::{PluginAbi, PluginDescriptor, ENGINE_VERSION, PLUGIN_ABI_VERSION};
+pub use discovery::{DiscoveryReport, PluginConfigs, PluginEntry, PluginManifest, RemoteEntry};
 use errors::*;
+#[cfg(unix)]
+use remote::RemotePlugin;
+#[cfg(unix)]
+pub use remote::{serve_remote_plugin, RemoteStatus};
+pub use state::PluginResource;
+use state::PluginState;
 use tb_ecs::*;
 
+mod descriptor;
+mod discovery;
+#[cfg(unix)]
+mod remote;
+mod state;
+
 mod errors {
+    use std::path::PathBuf;
+
     pub use tb_core::error::*;
 
-    error_chain! {}
+    error_chain! {
+        errors {
+            IncompatiblePlugin(reason: String) {
+                description("Plugin is built against an incompatible engine"),
+                display("Plugin is built against an incompatible engine: {}", reason),
+            }
+            NotAPlugin(path: PathBuf) {
+                description("Library has no plugin metadata"),
+                display("Library has no plugin metadata. path: {:?}", path),
+            }
+            PluginNotFound(lib_name: String, search_dirs: Vec<PathBuf>) {
+                description("Plugin library is not found"),
+                display("Plugin library is not found. name: {}, search dirs: {:?}", lib_name, search_dirs),
+            }
+            MissingDependency(plugin: String, dependency: String) {
+                description("Dependency of plugin is not found"),
+                display("Dependency of plugin is not found. plugin: {}, dependency: {}", plugin, dependency),
+            }
+            DependencyCycle {
+                description("Plugins depend on each other in a cycle"),
+                display("Plugins depend on each other in a cycle"),
+            }
+        }
+    }
 }
 
 pub trait Plugin: Any + Send + Sync {
     fn name(&self) -> &'static str;
-    fn on_load(&self) {}
-    fn on_unload(&self) {}
+    /// Insert the resources and event channels of the plugin here.
+    /// Called once its infos are registered and its state is restored, before its systems are set up.
+    fn on_load(&self, _world: &mut World) {}
+    /// Called before its systems are torn down and its state is saved
+    fn on_unload(&self, _world: &mut World) {}
     fn system_infos(&self) -> Box<dyn Iterator<Item = &'static SystemInfo>> {
         Box::new(inventory::iter::<SystemInfo>.into_iter())
     }
     fn component_infos(&self) -> Box<dyn Iterator<Item = &'static ComponentInfo>> {
         Box::new(inventory::iter::<ComponentInfo>.into_iter())
     }
+    /// The resources kept across reloads, they are removed from the world on unload
+    fn resources(&self) -> Vec<PluginResource> {
+        vec![]
+    }
 }
 
+/// Export the plugin with its descriptor, e.g.
+/// `declare_plugin!(Game {}, dependencies: ["physics"], optional_dependencies: ["audio"]);`
+/// Dependencies are the library names of other plugins.
 #[macro_export]
 macro_rules! declare_plugin {
     ($plugin:expr) => {
+        $crate::declare_plugin!($plugin, dependencies: []);
+    };
+    (
+        $plugin:expr,
+        dependencies: [$($dependency:expr),* $(,)?]
+        $(, optional_dependencies: [$($optional_dependency:expr),* $(,)?])?
+        $(,)?
+    ) => {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static _plugin_abi: $crate::PluginAbi = $crate::PluginAbi::CURRENT;
+
+        #[no_mangle]
+        pub fn _plugin_descriptor() -> $crate::PluginDescriptor {
+            $crate::PluginDescriptor {
+                name: env!("CARGO_CRATE_NAME").to_owned(),
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+                dependencies: vec![$($dependency.to_owned()),*],
+                optional_dependencies: vec![$($($optional_dependency.to_owned()),*)?],
+            }
+        }
+
         #[no_mangle]
         pub fn _plugin_create() -> Box<dyn Plugin> {
             Box::new($plugin)
@@ -38,7 +114,13 @@ impl LibPartner for Box<dyn Plugin> {
     type LoadResult = Result<Self>;
     type UnloadResult = Result<()>;
 
+    /// The abi is checked again, the library may be rebuilt since it was added
     fn load(lib: &Library) -> Self::LoadResult {
+        let abi: Symbol<*const PluginAbi> = unsafe {
+            lib.get(b"_plugin_abi")
+                .chain_err(|| ErrorKind::IncompatiblePlugin("no _plugin_abi symbol".to_owned()))?
+        };
+        unsafe { (**abi).check()? };
         type PluginCreate = fn() -> Box<dyn Plugin>;
         let plugin_create: Symbol<PluginCreate> = unsafe {
             lib.get(b"_plugin_create")
@@ -46,44 +128,245 @@ impl LibPartner for Box<dyn Plugin> {
         };
         let plugin: Box<dyn Plugin> = plugin_create();
         println!("Loaded plugin: {}", plugin.name());
-        plugin.on_load();
-        SystemRegistry::add_system_infos(plugin.system_infos());
-        ComponentRegistry::add_component_infos(plugin.component_infos());
+        CHANGED.with(|changed| changed.set(true));
+        ComponentRegistry::add_component_infos(plugin.name(), plugin.component_infos())
+            .chain_err(|| format!("Failed to register components of plugin: {}", plugin.name()))?;
+        if let Err(e) = SystemRegistry::add_system_infos(plugin.name(), plugin.system_infos()) {
+            // the plugin is not loaded, its components are not kept
+            let component_indices = ComponentRegistry::indices_of_owner(plugin.name());
+            if let Some((world, _scheduler)) = HOST.with(Cell::get) {
+                unsafe { &mut *world }.remove_component_types(&component_indices);
+            }
+            ComponentRegistry::remove_owner(plugin.name());
+            return Err(e)
+                .chain_err(|| format!("Failed to register systems of plugin: {}", plugin.name()));
+        }
+        match HOST.with(Cell::get) {
+            Some((world, _scheduler)) => {
+                let world = unsafe { &mut *world };
+                let state = SAVED_STATES.with(|states| states.borrow_mut().remove(plugin.name()));
+                if let Some(state) = state {
+                    state.restore(world, &plugin.resources());
+                }
+                plugin.on_load(world);
+            }
+            None => {
+                PENDING_ON_LOAD.with(|pending| pending.borrow_mut().push(plugin.name().to_owned()))
+            }
+        }
         Ok(plugin)
     }
 
+    /// The registered infos and the instances point into the library,
+    /// so they are removed before it is dropped.
+    /// The state is saved, and restored if the plugin is loaded again, e.g. by a hot reload.
     fn unload(&mut self, _lib: &Library) -> Self::UnloadResult {
         let name = self.name().to_owned();
-        self.on_unload();
+        PENDING_ON_LOAD.with(|pending| pending.borrow_mut().retain(|p| *p != name));
+        SystemRegistry::remove_owner(&name);
+        let component_indices = ComponentRegistry::indices_of_owner(&name);
+        let host = HOST.with(Cell::get);
+        if let Some((world, scheduler)) = host {
+            let world = unsafe { &mut *world };
+            self.on_unload(world);
+            unsafe { (*scheduler).refresh_systems(world) };
+            let state = PluginState::save(world, &component_indices, &self.resources());
+            world.remove_component_types(&component_indices);
+            SAVED_STATES.with(|states| states.borrow_mut().insert(name.clone(), state));
+        }
+        ComponentRegistry::remove_owner(&name);
+        CHANGED.with(|changed| changed.set(true));
+        if host.is_none() {
+            bail!(
+                "Plugin is unloaded outside PluginManager::update, its instances may outlive it: {}",
+                name
+            );
+        }
         println!("Unloaded plugin: {}", name);
         Ok(())
     }
 }
 
+thread_local! {
+    /// The world and scheduler of the running `PluginManager::update`
+    static HOST: Cell<Option<(*mut World, *mut Scheduler)>> = Cell::new(None);
+    /// Whether a plugin is loaded or unloaded since the systems are refreshed
+    static CHANGED: Cell<bool> = Cell::new(false);
+    /// The plugins loaded outside `PluginManager::update`, their `on_load` runs in the next one
+    static PENDING_ON_LOAD: RefCell<Vec<String>> = Default::default();
+    /// The states of the unloaded plugins by name
+    static SAVED_STATES: RefCell<HashMap<String, PluginState>> = Default::default();
+}
+
 pub struct PluginManager {
-    loader: Loader<Box<dyn Plugin>>,
+    /// Not dropped once plugins are loaded, see the `Drop` impl
+    loader: ManuallyDrop<Loader<Box<dyn Plugin>>>,
+    search_dirs: Vec<PathBuf>,
+    descriptors: HashMap<String, PluginDescriptor>,
+    pending_configs: Option<PluginConfigs>,
+    #[cfg(unix)]
+    remotes: Vec<RemotePlugin>,
+    /// The program with its arguments started for the remote plugins
+    #[cfg(unix)]
+    remote_host: (PathBuf, Vec<OsString>),
 }
 
 impl PluginManager {
+    /// Plugins are searched in `additional_search_dirs` and the dirs added later only
     pub fn new(additional_search_dirs: Vec<PathBuf>) -> Self {
         Self {
-            loader: Loader::new(additional_search_dirs).unwrap(),
+            loader: ManuallyDrop::new(Loader::new(additional_search_dirs.clone()).unwrap()),
+            search_dirs: additional_search_dirs,
+            descriptors: Default::default(),
+            pending_configs: None,
+            #[cfg(unix)]
+            remotes: vec![],
+            #[cfg(unix)]
+            remote_host: (
+                std::env::current_exe().unwrap_or_default(),
+                std::env::args_os().skip(1).collect(),
+            ),
         }
     }
 
     pub fn add_search_dir(&mut self, dir: PathBuf) {
+        self.search_dirs.insert(0, dir.clone());
         self.loader.add_search_dir(dir)
     }
 
-    pub fn update(&mut self) {
-        self.loader.update().unwrap();
+    /// Run the pending `on_load` hooks and reload the changed plugins, called between frames.
+    /// The systems, components and storages of an unloaded plugin are removed from the registries,
+    /// `world` and `scheduler` before its library is dropped.
+    pub fn update(&mut self, world: &mut World, scheduler: &mut Scheduler) {
+        if let Some(configs) = self.pending_configs.take() {
+            world.remove::<PluginConfigs>();
+            world.insert(|| configs);
+        }
+        HOST.with(|host| host.set(Some((world as *mut World, scheduler as *mut Scheduler))));
+        let pending = PENDING_ON_LOAD.with(|pending| std::mem::take(&mut *pending.borrow_mut()));
+        for name in pending {
+            if let Some(plugin) = self.plugin_by_name(&name) {
+                plugin.on_load(world);
+            }
+        }
+        let result = self.loader.update();
+        HOST.with(|host| host.set(None));
+        result.unwrap();
+        if CHANGED.with(|changed| changed.replace(false)) {
+            scheduler.refresh_systems(world);
+        }
+        #[cfg(unix)]
+        for remote in &mut self.remotes {
+            remote.update(world, &self.remote_host);
+        }
+    }
+
+    /// Run the plugin in a child process started from the remote host, see `set_remote_host`.
+    /// The storages of `components` and the `resources` are exchanged with it in each `update`,
+    /// it is restarted if it crashes.
+    #[cfg(unix)]
+    pub fn add_remote_plugin(
+        &mut self,
+        lib_name: &str,
+        components: Vec<String>,
+        resources: Vec<PluginResource>,
+    ) -> Result<()> {
+        let path = descriptor::find_library(lib_name, &self.search_dirs)?;
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.remotes
+            .push(RemotePlugin::new(lib_name, dir, components, resources));
+        Ok(())
     }
 
-    pub fn add_plugin(&mut self, lib_name: &str) {
+    /// The program started for the remote plugins, which should call `serve_remote_plugin` first.
+    /// Defaults to this executable with the same arguments.
+    #[cfg(unix)]
+    pub fn set_remote_host(&mut self, program: PathBuf, args: Vec<OsString>) {
+        self.remote_host = (program, args);
+    }
+
+    #[cfg(unix)]
+    pub fn remote_status(&self, lib_name: &str) -> Option<RemoteStatus> {
+        self.remotes
+            .iter()
+            .find(|remote| remote.lib_name() == lib_name)
+            .map(RemotePlugin::status)
+    }
+
+    fn plugin_by_name(&self, name: &str) -> Option<&dyn Plugin> {
+        self.descriptors
+            .keys()
+            .filter_map(|lib_name| self.get_plugin(lib_name))
+            .find(|plugin| plugin.name() == name)
+    }
+
+    /// `Plugin::on_load` runs in the next `update`
+    pub fn add_plugin(&mut self, lib_name: &str) -> Result<()> {
+        self.add_plugins(&[lib_name])
+    }
+
+    /// Load the plugins in dependency order.
+    /// Their descriptors are validated first, nothing is loaded if one of them is invalid.
+    pub fn add_plugins(&mut self, lib_names: &[&str]) -> Result<()> {
+        let mut descriptors = Vec::with_capacity(lib_names.len());
+        let mut inspected_libs = Vec::with_capacity(lib_names.len());
+        for &lib_name in lib_names {
+            if self.descriptors.contains_key(lib_name) {
+                continue;
+            }
+            let path = descriptor::find_library(lib_name, &self.search_dirs)?;
+            let (descriptor, lib) = descriptor::read_descriptor(lib_name, &path)
+                .chain_err(|| format!("Failed to read plugin: {}", lib_name))?;
+            descriptors.push(descriptor);
+            inspected_libs.push(lib);
+        }
+        let loaded = self.descriptors.keys().cloned().collect();
+        for descriptor in descriptor::resolve_load_order(&descriptors, &loaded)? {
+            self.load(descriptor)?;
+        }
+        Ok(())
+    }
+
+    /// Load the plugin built at `path`, e.g. by cargo, its dir is added to the search dirs.
+    /// A loaded plugin is reloaded once its library changes. Libraries which are not plugins,
+    /// or not enabled in `manifest` as local plugins, are ignored.
+    pub fn add_artifact(&mut self, path: &Path, manifest: Option<&PluginManifest>) -> Result<()> {
+        let lib_name = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(descriptor::library_name)
+            .ok_or_else(|| format!("Not a library: {:?}", path))?;
+        if let Some(dir) = path.parent() {
+            if !self.search_dirs.iter().any(|search_dir| search_dir == dir) {
+                self.add_search_dir(dir.to_owned());
+            }
+        }
+        if self.descriptors.contains_key(lib_name)
+            || !manifest.map_or(true, |manifest| manifest.is_local(lib_name))
+        {
+            return Ok(());
+        }
+        let (descriptor, _inspected_lib) = match descriptor::read_descriptor(lib_name, path) {
+            Err(e) if matches!(e.kind(), ErrorKind::NotAPlugin(_)) => return Ok(()),
+            inspected => inspected.chain_err(|| format!("Failed to read plugin: {}", lib_name))?,
+        };
+        let loaded = self.descriptors.keys().cloned().collect();
+        for descriptor in descriptor::resolve_load_order(&[descriptor], &loaded)? {
+            self.load(descriptor)?;
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, descriptor: PluginDescriptor) -> Result<()> {
         self.loader
-            .add_library(lib_name)
-            .chain_err(|| "Failed to load library")
-            .unwrap()
+            .add_library(&descriptor.name)
+            .chain_err(|| format!("Failed to load plugin: {}", descriptor.name))?;
+        self.descriptors.insert(descriptor.name.clone(), descriptor);
+        Ok(())
+    }
+
+    pub fn get_descriptor(&self, lib_name: &str) -> Option<&PluginDescriptor> {
+        self.descriptors.get(lib_name)
     }
 
     pub fn get_plugin(&self, lib_name: &str) -> Option<&dyn Plugin> {
@@ -99,13 +382,13 @@ impl Default for PluginManager {
     }
 }
 
-#[system]
-struct PluginSystem;
-
-impl<'s> System<'s> for PluginSystem {
-    type SystemData = Write<'s, PluginManager>;
-
-    fn run(&mut self, mut plugin_manager: Self::SystemData) {
-        plugin_manager.update()
+/// The components and resources of the plugins may outlive the manager in a world, e.g. the one
+/// returned by `Application::run_world`, and their drop code is in the libraries of the plugins.
+/// So the libraries of loaded plugins are kept open, only hot reloads in `update` close them.
+impl Drop for PluginManager {
+    fn drop(&mut self) {
+        if self.descriptors.is_empty() {
+            unsafe { ManuallyDrop::drop(&mut self.loader) };
+        }
     }
 }
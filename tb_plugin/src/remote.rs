@@ -0,0 +1,361 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, ErrorKind as IoErrorKind, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tb_core::serde_json;
+use tb_ecs::*;
+
+use crate::errors::*;
+use crate::state::PluginState;
+use crate::{PluginManager, PluginResource};
+
+const SOCKET_VAR: &str = "TB_REMOTE_PLUGIN_SOCKET";
+const NAME_VAR: &str = "TB_REMOTE_PLUGIN_NAME";
+const DIR_VAR: &str = "TB_REMOTE_PLUGIN_DIR";
+/// A plugin process not connected or not answering a frame in time is taken as crashed
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Crashes in a row before a remote plugin is given up
+const MAX_RESTARTS: usize = 3;
+
+/// Sent to the plugin process each frame
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    entities: Vec<Entity>,
+    components: Vec<String>,
+    state: PluginState,
+}
+
+/// Newline delimited json over a unix socket
+struct Connection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Connection {
+    fn new(stream: UnixStream) -> Result<Self> {
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .chain_err(|| "Failed to set read timeout")?;
+        let writer = stream.try_clone().chain_err(|| "Failed to clone stream")?;
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+        })
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> Result<()> {
+        let mut line = serde_json::to_vec(message).chain_err(|| "Failed to serialize message")?;
+        line.push(b'\n');
+        self.writer
+            .write_all(&line)
+            .chain_err(|| "Failed to send message")
+    }
+
+    /// `None` if the other side is closed
+    fn receive<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        let mut line = String::new();
+        if self
+            .reader
+            .read_line(&mut line)
+            .chain_err(|| "Failed to receive message")?
+            == 0
+        {
+            return Ok(None);
+        }
+        serde_json::from_str(&line)
+            .map(Some)
+            .chain_err(|| "Failed to deserialize message")
+    }
+}
+
+fn component_indices(names: &[String]) -> Vec<ComponentIndex> {
+    names
+        .iter()
+        .filter_map(|name| ComponentIndex::by_name(name))
+        .filter(|&index| ComponentRegistry::info(index).is_some())
+        .collect()
+}
+
+/// Whether the remote plugin is running, see `PluginManager::remote_status`
+#[derive(Copy, Clone, Debug)]
+pub struct RemoteStatus {
+    pub running: bool,
+    pub crashes: usize,
+    /// Restarted no more after crashing too many times in a row
+    pub given_up: bool,
+}
+
+/// A plugin run in a child process, so that it can't take down the engine.
+/// The storages of the listed component types and the resources are exchanged with it each frame,
+/// the types unknown on one side are kept as json and sent back, also after a restart.
+/// Only the storages and resources changed on one side are applied on the other.
+/// Entities created by the plugin are reported and dropped, killing an entity there only removes
+/// its exchanged components here.
+pub(crate) struct RemotePlugin {
+    lib_name: String,
+    dir: PathBuf,
+    components: Vec<String>,
+    resources: Vec<PluginResource>,
+    /// The last state answered by the plugin
+    state: PluginState,
+    process: Option<(Child, Connection)>,
+    crashes: usize,
+    crashes_in_row: usize,
+}
+
+impl RemotePlugin {
+    pub(crate) fn new(
+        lib_name: &str,
+        dir: PathBuf,
+        components: Vec<String>,
+        resources: Vec<PluginResource>,
+    ) -> Self {
+        Self {
+            lib_name: lib_name.to_owned(),
+            dir,
+            components,
+            resources,
+            state: Default::default(),
+            process: None,
+            crashes: 0,
+            crashes_in_row: 0,
+        }
+    }
+
+    pub(crate) fn lib_name(&self) -> &str {
+        &self.lib_name
+    }
+
+    pub(crate) fn status(&self) -> RemoteStatus {
+        RemoteStatus {
+            running: self.process.is_some(),
+            crashes: self.crashes,
+            given_up: self.crashes_in_row > MAX_RESTARTS,
+        }
+    }
+
+    /// Run a frame of the plugin, which is started first if it is not running
+    pub(crate) fn update(&mut self, world: &mut World, host: &(PathBuf, Vec<OsString>)) {
+        if self.status().given_up {
+            return;
+        }
+        let result = match self.process.take() {
+            Some(process) => Ok(process),
+            None => self.start(host),
+        }
+        .and_then(|mut process| {
+            let states = self.exchange(world, &mut process.1);
+            self.process = Some(process);
+            states
+        });
+        match result {
+            Ok((sent, answered)) => {
+                self.crashes_in_row = 0;
+                self.apply(world, &sent, answered);
+            }
+            Err(e) => self.on_crash(e),
+        }
+    }
+
+    fn start(&self, (program, args): &(PathBuf, Vec<OsString>)) -> Result<(Child, Connection)> {
+        let socket = std::env::temp_dir().join(format!(
+            "tb_plugin_{}_{}.sock",
+            std::process::id(),
+            self.lib_name
+        ));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket)
+            .chain_err(|| format!("Failed to bind socket: {:?}", socket))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .env(SOCKET_VAR, &socket)
+            .env(NAME_VAR, &self.lib_name)
+            .env(DIR_VAR, &self.dir)
+            .spawn()
+            .chain_err(|| format!("Failed to start plugin process: {:?}", program))?;
+        let accepted = accept(&listener, &mut child);
+        let _ = std::fs::remove_file(&socket);
+        match accepted.and_then(Connection::new) {
+            Ok(connection) => Ok((child, connection)),
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the state sent and the one answered
+    fn exchange(
+        &self,
+        world: &mut World,
+        connection: &mut Connection,
+    ) -> Result<(PluginState, PluginState)> {
+        world.maintain();
+        let component_indices = component_indices(&self.components);
+        let mut state = self.state.clone();
+        state.merge(PluginState::capture(
+            world,
+            &component_indices,
+            &self.resources,
+        ));
+        let frame = Frame {
+            entities: world.insert(Entities::default).iter().collect(),
+            components: self.components.clone(),
+            state,
+        };
+        connection.send(&frame)?;
+        let answered = connection
+            .receive()?
+            .ok_or_else(|| Error::from("Plugin process is closed"))?;
+        Ok((frame.state, answered))
+    }
+
+    /// Restore the storages and resources the plugin changed from the ones sent
+    fn apply(&mut self, world: &mut World, sent: &PluginState, answered: PluginState) {
+        answered
+            .clone()
+            .changed_since(sent)
+            .restore(world, &self.resources);
+        self.state = answered;
+    }
+
+    fn on_crash(&mut self, error: Error) {
+        if let Some((mut child, _)) = self.process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.crashes += 1;
+        self.crashes_in_row += 1;
+        if self.status().given_up {
+            println!(
+                "Remote plugin {} crashed {} times in a row, given up: {}",
+                self.lib_name,
+                self.crashes_in_row,
+                error.display_chain()
+            );
+        } else {
+            println!(
+                "Remote plugin {} crashed, restarting: {}",
+                self.lib_name,
+                error.display_chain()
+            );
+        }
+    }
+}
+
+impl Drop for RemotePlugin {
+    fn drop(&mut self) {
+        if let Some((mut child, _)) = self.process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Wait for the plugin process to connect, failing if it exits first
+fn accept(listener: &UnixListener, child: &mut Child) -> Result<UnixStream> {
+    listener
+        .set_nonblocking(true)
+        .chain_err(|| "Failed to set socket nonblocking")?;
+    let start = Instant::now();
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream
+                    .set_nonblocking(false)
+                    .chain_err(|| "Failed to set stream blocking")?;
+                return Ok(stream);
+            }
+            Err(e) if e.kind() == IoErrorKind::WouldBlock => {}
+            Err(e) => return Err(e).chain_err(|| "Failed to accept plugin process"),
+        }
+        if let Some(status) = child
+            .try_wait()
+            .chain_err(|| "Failed to wait plugin process")?
+        {
+            bail!("Plugin process exited before connecting: {}", status);
+        }
+        if start.elapsed() > TIMEOUT {
+            bail!("Plugin process is not connected in time");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Serve the plugin if this process is started by `PluginManager` for a remote plugin.
+/// Call it first thing in `main` of the host program, `None` if this is not a plugin process.
+pub fn serve_remote_plugin() -> Option<Result<()>> {
+    let socket = PathBuf::from(std::env::var_os(SOCKET_VAR)?);
+    let lib_name = std::env::var(NAME_VAR).ok()?;
+    let dir = PathBuf::from(std::env::var_os(DIR_VAR)?);
+    Some(serve(&socket, &lib_name, dir))
+}
+
+fn serve(socket: &Path, lib_name: &str, dir: PathBuf) -> Result<()> {
+    let stream = UnixStream::connect(socket)
+        .chain_err(|| format!("Failed to connect engine: {:?}", socket))?;
+    let mut connection = Connection::new(stream)?;
+    // the engine may wait for other plugins between frames
+    connection
+        .reader
+        .get_ref()
+        .set_read_timeout(None)
+        .chain_err(|| "Failed to set read timeout")?;
+    let mut plugin_manager = PluginManager::new(vec![dir]);
+    plugin_manager.add_plugin(lib_name)?;
+    let mut world = World::default();
+    let mut scheduler = Scheduler::new(&mut world);
+    let mut answered = PluginState::default();
+    while let Some(frame) = connection.receive::<Frame>()? {
+        let resources = plugin_manager
+            .get_plugin(lib_name)
+            .map(|plugin| plugin.resources())
+            .unwrap_or_default();
+        let component_indices = component_indices(&frame.components);
+        world.mirror_entities(&frame.entities);
+        frame
+            .state
+            .changed_since(&answered)
+            .restore(&mut world, &resources);
+
+        plugin_manager.update(&mut world, &mut scheduler);
+        scheduler.update(&mut world);
+
+        reject_created_entities(&mut world, lib_name, &frame.entities);
+        answered = PluginState::capture(&world, &component_indices, &resources);
+        connection.send(&answered)?;
+    }
+    scheduler.shutdown(&mut world);
+    Ok(())
+}
+
+/// The entities of the plugin process follow the engine, the ones the plugin created are killed
+fn reject_created_entities(world: &mut World, lib_name: &str, sent: &[Entity]) {
+    world.maintain();
+    let sent: HashSet<Entity> = sent.iter().copied().collect();
+    let entities = world.insert(Entities::default);
+    let created = entities
+        .iter()
+        .filter(|entity| !sent.contains(entity))
+        .count();
+    let killed = sent
+        .iter()
+        .filter(|&&entity| !entities.is_alive(entity))
+        .count();
+    if created > 0 || killed > 0 {
+        println!(
+            "Remote plugin {} created {} and killed {} entities, which are not sent back",
+            lib_name, created, killed
+        );
+    }
+    if created > 0 {
+        world.mirror_entities(&sent.into_iter().collect::<Vec<_>>());
+    }
+}
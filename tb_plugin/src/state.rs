@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tb_core::serde_json::{self, Value};
+use tb_ecs::*;
+
+use crate::errors::*;
+
+/// A resource of a plugin, kept across reloads by its stable name
+pub struct PluginResource {
+    name: &'static str,
+    save: fn(&World) -> Option<serde_json::Result<Value>>,
+    remove: fn(&mut World),
+    restore: fn(&mut World, Value) -> serde_json::Result<()>,
+}
+
+impl PluginResource {
+    pub fn new<R: Resource + Serialize + DeserializeOwned>(name: &'static str) -> Self {
+        Self {
+            name,
+            save: save_resource::<R>,
+            remove: remove_resource::<R>,
+            restore: restore_resource::<R>,
+        }
+    }
+}
+
+fn save_resource<R: Resource + Serialize>(world: &World) -> Option<serde_json::Result<Value>> {
+    Some(serde_json::to_value(
+        unsafe { world.try_fetch::<R>() }.ok()?,
+    ))
+}
+
+fn remove_resource<R: Resource>(world: &mut World) {
+    world.remove::<R>();
+}
+
+fn restore_resource<R: Resource + DeserializeOwned>(
+    world: &mut World,
+    value: Value,
+) -> serde_json::Result<()> {
+    let resource: R = serde_json::from_value(value)?;
+    world.remove::<R>();
+    world.insert(|| resource);
+    Ok(())
+}
+
+/// The component storages and resources of an unloaded plugin, restored when it is loaded again.
+/// Also exchanged with a remote plugin each frame.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub(crate) struct PluginState {
+    components: ComponentsSnapshot,
+    resources: HashMap<String, Value>,
+}
+
+impl PluginState {
+    /// Save the storages of the components and remove the resources from the world,
+    /// or they would be dropped after their library
+    pub(crate) fn save(
+        world: &mut World,
+        component_indices: &[ComponentIndex],
+        resources: &[PluginResource],
+    ) -> Self {
+        let state = Self::capture(world, component_indices, resources);
+        for resource in resources {
+            (resource.remove)(world);
+        }
+        state
+    }
+
+    /// Save the storages of the components and the resources, leaving the world untouched.
+    /// What fails to save is reported and dropped.
+    pub(crate) fn capture(
+        world: &World,
+        component_indices: &[ComponentIndex],
+        resources: &[PluginResource],
+    ) -> Self {
+        let mut components = ComponentsSnapshot::default();
+        if let Err(e) = world.save_component_types(component_indices, &mut components) {
+            println!("Failed to save components: {}", e.display_chain());
+        }
+        let mut saved = HashMap::with_capacity(resources.len());
+        for resource in resources {
+            match (resource.save)(world) {
+                Some(Ok(value)) => {
+                    saved.insert(resource.name.to_owned(), value);
+                }
+                Some(Err(e)) => println!("Failed to save resource: {}, {}", resource.name, e),
+                None => {}
+            }
+        }
+        Self {
+            components,
+            resources: saved,
+        }
+    }
+
+    /// Overwrite the saved storages and resources with the ones of `other`
+    pub(crate) fn merge(&mut self, other: Self) {
+        self.components.extend(other.components);
+        self.resources.extend(other.resources);
+    }
+
+    /// Drop the storages and resources equal to the ones of `previous`,
+    /// e.g. to apply only what the other side of a remote plugin changed
+    pub(crate) fn changed_since(mut self, previous: &Self) -> Self {
+        self.components
+            .retain(|name, value| previous.components.get(name) != Some(value));
+        self.resources
+            .retain(|name, value| previous.resources.get(name) != Some(value));
+        self
+    }
+
+    /// Restore into the types registered by the same names, matched by the reloaded plugin.
+    /// The storages are replaced in place, see `World::restore_component_types`.
+    /// What fails to restore is reported and dropped.
+    pub(crate) fn restore(mut self, world: &mut World, resources: &[PluginResource]) {
+        for resource in resources {
+            if let Some(value) = self.resources.remove(resource.name) {
+                if let Err(e) = (resource.restore)(world, value) {
+                    println!("Failed to restore resource: {}, {}", resource.name, e);
+                }
+            }
+        }
+        if let Err(e) = world.restore_component_types(self.components) {
+            println!("Failed to restore components: {}", e.display_chain());
+        }
+    }
+}
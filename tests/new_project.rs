@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use toybox::*;
+
+/// Build the new project and run its test offline, against the local workspace.
+/// The dependencies are pinned by the lock file of the workspace, so they are in the cache.
+#[test]
+fn build_new_project() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let scaffold_dir = root.join("target/scaffold");
+    let project_dir = scaffold_dir.join("spin_game");
+    if project_dir.exists() {
+        std::fs::remove_dir_all(&project_dir).unwrap();
+    }
+
+    new_project(&project_dir, "spin_game", &root).unwrap();
+    for file in &[
+        "Cargo.toml",
+        "src/lib.rs",
+        "plugins.json",
+        "toybox.json",
+        "assets/levels/entry.tbasset",
+    ] {
+        assert!(project_dir.join(file).exists(), "{} is not created", file);
+    }
+    assert!(new_project(&project_dir, "spin_game", &root).is_err());
+    assert!(new_project(&scaffold_dir.join("invalid"), "Spin-Game", &root).is_err());
+
+    let lock_file = root.join("Cargo.lock");
+    if lock_file.exists() {
+        std::fs::copy(lock_file, project_dir.join("Cargo.lock")).unwrap();
+    }
+    let target_dir = scaffold_dir.join("target");
+    for command in &["build", "test"] {
+        let status = Command::new(env!("CARGO"))
+            .current_dir(&project_dir)
+            .args(&[*command, "--offline"])
+            .env("CARGO_TARGET_DIR", &target_dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "cargo {} failed", command);
+    }
+    let library = format!(
+        "{}spin_game{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    );
+    assert!(target_dir.join("debug").join(library).exists());
+}
@@ -1,4 +1,6 @@
 mod load_ecs_info {
+    use std::path::Path;
+
     use toybox::*;
 
     error_chain! {}
@@ -14,9 +16,17 @@ mod load_ecs_info {
 
     #[test]
     fn load_ecs_info() -> Result<()> {
-        let mut plugin_manager = PluginManager::default();
-        plugin_manager.add_plugin("script_ts");
-        plugin_manager.add_plugin("example_pong");
+        // tests run in `deps` next to the libraries of the workspace
+        let exe = std::env::current_exe().unwrap();
+        let mut plugin_manager = PluginManager::new(
+            exe.ancestors()
+                .skip(1)
+                .take(2)
+                .map(Path::to_path_buf)
+                .collect(),
+        );
+        plugin_manager.add_plugin("script_ts").unwrap();
+        plugin_manager.add_plugin("example_pong").unwrap();
 
         for system in inventory::iter::<SystemInfo> {
             println!(
@@ -29,3 +39,297 @@ mod load_ecs_info {
         Ok(())
     }
 }
+
+mod fixture {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    /// Built apart from the test target, the running `cargo test` locks that one.
+    /// Each test has its own, as they rebuild it while running.
+    pub fn target_dir(test: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("target/reload_fixture")
+            .join(test)
+    }
+
+    pub fn build(test: &str, step: i32) {
+        let status = Command::new(env!("CARGO"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(&["build", "-p", "reload_fixture"])
+            .env("CARGO_TARGET_DIR", target_dir(test))
+            .env("RELOAD_FIXTURE_STEP", step.to_string())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
+
+mod discovery {
+    use toybox::*;
+
+    use crate::fixture;
+
+    #[test]
+    fn without_manifest() {
+        fixture::build("discovery", 1);
+        let mut plugin_manager =
+            PluginManager::new(vec![fixture::target_dir("discovery").join("debug")]);
+        let report = plugin_manager.discover(None);
+        assert_eq!(report.loaded, vec!["reload_fixture".to_owned()]);
+        assert!(report.failed.is_empty());
+        // the plugins next to the test executable are not scanned
+        let found: Vec<_> = report.found.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(found, vec!["reload_fixture"]);
+        assert!(plugin_manager.get_plugin("example_pong").is_none());
+    }
+}
+
+mod hot_reload {
+    use std::time::{Duration, Instant};
+
+    use toybox::*;
+
+    use crate::fixture;
+
+    fn counter_field(world: &mut World, entity: Entity, field: &str) -> i64 {
+        world
+            .reflect_component(entity, "reload_fixture.Counter")
+            .unwrap()
+            .get_field(field)
+            .unwrap()
+            .as_i64()
+            .unwrap()
+    }
+
+    #[test]
+    fn state_survives_reload() {
+        fixture::build("hot_reload", 1);
+        let mut world = World::default();
+        let mut scheduler = Scheduler::new(&mut world);
+        let mut plugin_manager =
+            PluginManager::new(vec![fixture::target_dir("hot_reload").join("debug")]);
+        plugin_manager.add_plugin("reload_fixture").unwrap();
+        plugin_manager.update(&mut world, &mut scheduler);
+        let entity = world
+            .spawn_from_json(&serde_json::json!({
+                "reload_fixture.Counter": { "value": 0, "runs": 0 }
+            }))
+            .unwrap();
+        let engine_entity = world
+            .create_entity()
+            .with(Location::new(1.0, 2.0, 3.0))
+            .create();
+        scheduler.update(&mut world);
+        scheduler.update(&mut world);
+        assert_eq!(counter_field(&mut world, entity, "value"), 2);
+        assert_eq!(counter_field(&mut world, entity, "runs"), 2);
+
+        fixture::build("hot_reload", 10);
+        let start = Instant::now();
+        loop {
+            assert!(start.elapsed() < Duration::from_secs(30), "not reloaded");
+            let value = counter_field(&mut world, entity, "value");
+            let runs = counter_field(&mut world, entity, "runs");
+            plugin_manager.update(&mut world, &mut scheduler);
+            scheduler.update(&mut world);
+            if counter_field(&mut world, entity, "value") == value + 10 {
+                assert_eq!(counter_field(&mut world, entity, "runs"), runs + 1);
+                // the engine components the plugin links are not unloaded with it
+                let location = ComponentIndex::get::<Location>();
+                assert!(ComponentRegistry::info(location).is_some());
+                assert!(ComponentRegistry::indices_of_owner("reload_fixture")
+                    .iter()
+                    .all(|&index| index != location));
+                assert_eq!(
+                    unsafe { world.fetch::<ComponentStorage<Location>>() }
+                        .fetch(engine_entity)
+                        .map(|location| location.location),
+                    Some(Point3::new(1.0, 2.0, 3.0))
+                );
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+mod hooks {
+    use std::time::{Duration, Instant};
+
+    use toybox::*;
+
+    use crate::fixture;
+
+    fn take_log(world: &mut World) -> Vec<String> {
+        std::mem::take(unsafe { world.fetch_mut::<Vec<String>>() })
+    }
+
+    /// `on_load` sees the restored state before the systems are set up,
+    /// `on_unload` sees the storages before the systems are torn down and the storages removed
+    #[test]
+    fn hook_order() {
+        fixture::build("hooks", 1);
+        let mut world = World::default();
+        world.insert(Vec::<String>::new);
+        let mut scheduler = Scheduler::new(&mut world);
+        let mut plugin_manager =
+            PluginManager::new(vec![fixture::target_dir("hooks").join("debug")]);
+        plugin_manager.add_plugin("reload_fixture").unwrap();
+        plugin_manager.update(&mut world, &mut scheduler);
+        assert_eq!(
+            take_log(&mut world),
+            vec![
+                "on_load runs:false counters:None",
+                "setup runs:true counters:Some(0)"
+            ]
+        );
+        world
+            .spawn_from_json(&serde_json::json!({
+                "reload_fixture.Counter": { "value": 0, "runs": 0 }
+            }))
+            .unwrap();
+        scheduler.update(&mut world);
+
+        fixture::build("hooks", 10);
+        let start = Instant::now();
+        let log = loop {
+            assert!(start.elapsed() < Duration::from_secs(30), "not reloaded");
+            std::thread::sleep(Duration::from_millis(100));
+            plugin_manager.update(&mut world, &mut scheduler);
+            let log = take_log(&mut world);
+            if !log.is_empty() {
+                break log;
+            }
+        };
+        assert_eq!(
+            log,
+            vec![
+                "on_unload runs:true counters:Some(1)",
+                "teardown runs:true counters:Some(1)",
+                "on_load runs:true counters:Some(1)",
+                "setup runs:true counters:Some(1)",
+            ]
+        );
+    }
+}
+
+mod headless {
+    use std::time::Instant;
+
+    use toybox::*;
+
+    use crate::fixture;
+
+    #[test]
+    fn run_frames() {
+        fixture::build("headless", 1);
+        let mut app = Application::default()
+            .with_frame_limit(600)
+            .with_simulated_clock();
+        let plugin_manager = app.plugin_manager();
+        plugin_manager.add_search_dir(fixture::target_dir("headless").join("debug"));
+        plugin_manager.add_plugin("reload_fixture").unwrap();
+        let mut world = World::default();
+        let mut scheduler = Scheduler::new(&mut world);
+        plugin_manager.update(&mut world, &mut scheduler);
+        let entity = world
+            .spawn_from_json(&serde_json::json!({
+                "reload_fixture.Counter": { "value": 0, "runs": 0 }
+            }))
+            .unwrap();
+
+        let start = Instant::now();
+        let mut world = app.run_world(world);
+        let counter = world
+            .reflect_component(entity, "reload_fixture.Counter")
+            .unwrap();
+        assert_eq!(counter.get_field("value").unwrap().as_i64(), Some(600));
+        assert_eq!(counter.get_field("runs").unwrap().as_i64(), Some(600));
+        let time = unsafe { world.fetch::<Time>() };
+        assert_eq!(time.frame(), 600);
+        assert_eq!(time.elapsed(), time.delta() * 600);
+        // not paced by the wall clock
+        assert!(start.elapsed() < time.elapsed());
+
+        world.insert(|| AppExit);
+        let mut world = app.run_world(world);
+        assert_eq!(unsafe { world.fetch::<Time>() }.frame(), 601);
+
+        // the components of the plugin are dropped with the world, after the application
+        drop(app);
+        let counter = world
+            .reflect_component(entity, "reload_fixture.Counter")
+            .unwrap();
+        assert_eq!(counter.get_field("runs").unwrap().as_i64(), Some(601));
+        drop(world);
+    }
+}
+
+#[cfg(unix)]
+mod remote {
+    use toybox::*;
+
+    use crate::fixture;
+
+    /// The same resource as the one of the fixture, by name
+    #[derive(Default, Serialize, Deserialize)]
+    struct Runs {
+        count: u32,
+        panic_at: Option<u32>,
+    }
+
+    /// Started by the engine side with the plugin to serve, passes otherwise
+    #[test]
+    fn host() {
+        if let Some(result) = serve_remote_plugin() {
+            result.unwrap();
+        }
+    }
+
+    #[test]
+    fn restart_after_crash() {
+        fixture::build("remote", 1);
+        let mut world = World::default();
+        let mut scheduler = Scheduler::new(&mut world);
+        let mut plugin_manager =
+            PluginManager::new(vec![fixture::target_dir("remote").join("debug")]);
+        plugin_manager.set_remote_host(
+            std::env::current_exe().unwrap(),
+            vec![
+                "--exact".into(),
+                "remote::host".into(),
+                "--nocapture".into(),
+            ],
+        );
+        plugin_manager
+            .add_remote_plugin(
+                "reload_fixture",
+                vec!["reload_fixture.Counter".to_owned()],
+                vec![PluginResource::new::<Runs>("reload_fixture.Runs")],
+            )
+            .unwrap();
+        world.insert(Runs::default);
+        let runs = |world: &World| unsafe { world.fetch::<Runs>() }.count;
+
+        plugin_manager.update(&mut world, &mut scheduler);
+        plugin_manager.update(&mut world, &mut scheduler);
+        assert_eq!(runs(&world), 2);
+        let status = plugin_manager.remote_status("reload_fixture").unwrap();
+        assert!(status.running);
+        assert_eq!(status.crashes, 0);
+
+        unsafe { world.fetch_mut::<Runs>() }.panic_at = Some(3);
+        plugin_manager.update(&mut world, &mut scheduler);
+        let status = plugin_manager.remote_status("reload_fixture").unwrap();
+        assert!(!status.running);
+        assert_eq!(status.crashes, 1);
+        assert_eq!(runs(&world), 2);
+
+        unsafe { world.fetch_mut::<Runs>() }.panic_at = None;
+        plugin_manager.update(&mut world, &mut scheduler);
+        let status = plugin_manager.remote_status("reload_fixture").unwrap();
+        assert!(status.running);
+        assert!(!status.given_up);
+        assert_eq!(runs(&world), 3);
+    }
+}